@@ -0,0 +1,312 @@
+/// Groups of Unknown Order.
+/// Abstracts the group operations so that accumulators can be built over an RSA group(requires a
+/// trusted setup) or a class group of binary quadratic forms(no trusted setup required).
+
+use runtime_io::blake2_256;
+use crate::subroutines;
+use super::U2048;
+
+/// A group of unknown order. Exponents are U2048 values since accumulated elements are products
+/// of primes.
+pub trait Group {
+    type Elem: Copy + PartialEq;
+
+    fn identity() -> Self::Elem;
+
+    fn op(a: Self::Elem, b: Self::Elem) -> Self::Elem;
+
+    /// Needed for non-membership witnesses since Bezout coefficients can be negative.
+    fn inverse(elem: Self::Elem) -> Self::Elem;
+
+    /// Left-to-right square and multiply.
+    fn exp(base: Self::Elem, exp: U2048) -> Self::Elem {
+        let mut result = Self::identity();
+        for i in (0..exp.bits()).rev() {
+            result = Self::op(result, result);
+            if exp.bit(i) {
+                result = Self::op(result, base);
+            }
+        }
+        return result;
+    }
+
+    /// Deterministically derives a group element from a seed.
+    fn hash_to_generator(seed: &[u8]) -> Self::Elem;
+}
+
+/// The RSA group defined by MODULUS.
+pub struct RsaGroup;
+
+impl Group for RsaGroup {
+    type Elem = U2048;
+
+    fn identity() -> U2048 {
+        return U2048::from(1);
+    }
+
+    fn op(a: U2048, b: U2048) -> U2048 {
+        return subroutines::mul_mod(a, b, U2048::from_dec_str(super::MODULUS).unwrap());
+    }
+
+    fn inverse(elem: U2048) -> U2048 {
+        return subroutines::mod_inverse(elem);
+    }
+
+    fn exp(base: U2048, exp: U2048) -> U2048 {
+        return subroutines::mod_exp(base, exp, U2048::from_dec_str(super::MODULUS).unwrap());
+    }
+
+    /// Maps the hash of the seed into [2, MODULUS).
+    fn hash_to_generator(seed: &[u8]) -> U2048 {
+        let modulus = U2048::from_dec_str(super::MODULUS).unwrap();
+        return U2048::from_little_endian(&blake2_256(seed)) % (modulus - U2048::from(2)) + U2048::from(2);
+    }
+}
+
+/// Discriminant of the class group. Must be negative and equal to 1 mod 4.
+/// NOTE: This is a toy ~40 bit discriminant so that forms fit in i128 arithmetic. Secure parameters
+/// require discriminants of at least 1024 bits and signed arbitrary-precision integers.
+pub const DISCRIMINANT: i128 = -1099511627791;
+
+/// A reduced binary quadratic form (a, b, c) with b^2 - 4ac = DISCRIMINANT.
+pub type Form = (i128, i128, i128);
+
+/// The class group of binary quadratic forms with discriminant DISCRIMINANT.
+pub struct ClassGroup;
+
+impl Group for ClassGroup {
+    type Elem = Form;
+
+    fn identity() -> Form {
+        return (1, 1, (1 - DISCRIMINANT) / 4);
+    }
+
+    /// Composition of forms. Algorithm courtesy of the Chia VDF competition reference implementation.
+    fn op(f1: Form, f2: Form) -> Form {
+        let (a1, b1, c1) = f1;
+        let (a2, b2, _) = f2;
+        let g = (b2 + b1) / 2;
+        let h = (b2 - b1) / 2;
+        let w = gcd(gcd(a1, a2), g);
+
+        let j = w;
+        let s = a1 / w;
+        let t = a2 / w;
+        let u = g / w;
+
+        let (k_temp, constant_factor) = solve_mod(t * u, h * u + s * c1, s * t);
+        let (n, _) = solve_mod(t * constant_factor, h - t * k_temp, s);
+        let k = k_temp + constant_factor * n;
+        let l = (t * k - h) / s;
+        let m = (t * u * k - h * u - s * c1) / (s * t);
+        return reduce((s * t, j * u - (k * t + l * s), k * l - j * m));
+    }
+
+    fn inverse(f: Form) -> Form {
+        let (a, b, c) = f;
+        return reduce((a, -b, c));
+    }
+
+    /// Finds the first prime p >= hash(seed) for which DISCRIMINANT is a square mod p and returns
+    /// the reduced form (p, b, c).
+    fn hash_to_generator(seed: &[u8]) -> Form {
+        let hash = blake2_256(seed);
+        let mut p = (hash[0] as i128 | (hash[1] as i128) << 8) | 1;
+        loop {
+            if p > 2 && is_prime(p) {
+                let residue = DISCRIMINANT.rem_euclid(p);
+                for mut b in 0..p {
+                    if (b * b) % p == residue {
+                        // b must be odd so that b^2 = DISCRIMINANT mod 4p
+                        if b % 2 == 0 {
+                            b = p - b;
+                        }
+                        return reduce((p, b, (b * b - DISCRIMINANT) / (4 * p)));
+                    }
+                }
+            }
+            p += 2;
+        }
+    }
+}
+
+fn gcd(mut a: i128, mut b: i128) -> i128 {
+    a = a.abs();
+    b = b.abs();
+    while b != 0 {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+    return a;
+}
+
+/// Extended Euclidean algorithm for non-negative inputs. Returns (gcd, x, y) where gcd = xa + yb.
+fn xgcd(a: i128, b: i128) -> (i128, i128, i128) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_s, mut s) = (1, 0);
+    let (mut old_t, mut t) = (0, 1);
+    while r != 0 {
+        let quotient = old_r / r;
+        let new_r = old_r - quotient * r;
+        old_r = r;
+        r = new_r;
+        let new_s = old_s - quotient * s;
+        old_s = s;
+        s = new_s;
+        let new_t = old_t - quotient * t;
+        old_t = t;
+        t = new_t;
+    }
+    return (old_r, old_s, old_t);
+}
+
+/// Solves ax = b mod m. Returns (x_0, step) such that every solution equals x_0 + n*step.
+fn solve_mod(a: i128, b: i128, m: i128) -> (i128, i128) {
+    let (g, mut d, _) = xgcd(a.abs(), m);
+    if a < 0 {
+        d = -d;
+    }
+    let q = b / g;
+    return ((q * d).rem_euclid(m), m / g);
+}
+
+/// Moves b into the range (-a, a].
+fn normalize(f: Form) -> Form {
+    let (a, b, c) = f;
+    if -a < b && b <= a {
+        return f;
+    }
+    let r = (a - b).div_euclid(2 * a);
+    return (a, b + 2 * r * a, a * r * r + b * r + c);
+}
+
+/// Reduces a form to the unique representative of its class.
+fn reduce(f: Form) -> Form {
+    let (mut a, mut b, mut c) = normalize(f);
+    while a > c || (a == c && b < 0) {
+        let (new_a, new_b, new_c) = normalize((c, -b, a));
+        a = new_a;
+        b = new_b;
+        c = new_c;
+    }
+    return (a, b, c);
+}
+
+/// Trial division. Only used for the small primes in hash_to_generator.
+fn is_prime(n: i128) -> bool {
+    if n < 2 {
+        return false;
+    }
+    let mut i = 2;
+    while i * i <= n {
+        if n % i == 0 {
+            return false;
+        }
+        i += 1;
+    }
+    return true;
+}
+
+/// Equivalent to witnesses::mem_wit_create in an arbitrary group.
+pub fn mem_wit_create<G: Group>(old_state: G::Elem, agg: U2048, elem: U2048) -> Option<G::Elem> {
+    if agg % elem != U2048::from(0) {
+        return None;
+    }
+    return Some(G::exp(old_state, agg / elem));
+}
+
+/// Equivalent to witnesses::verify_mem_wit in an arbitrary group.
+pub fn verify_mem_wit<G: Group>(state: G::Elem, witness: G::Elem, elem: U2048) -> bool {
+    return G::exp(witness, elem) == state;
+}
+
+/// Equivalent to witnesses::non_mem_wit_create in an arbitrary group. Returns None if "elem" is
+/// not coprime to "agg_elems".
+pub fn non_mem_wit_create<G: Group>(mut old_state: G::Elem, agg_elems: U2048, elem: U2048) -> Option<(U2048, bool, G::Elem)> {
+    let pair = subroutines::bezout(agg_elems, elem)?;
+    if pair.sign_b {
+        old_state = G::inverse(old_state);
+    }
+    return Some((pair.coefficient_a, pair.sign_a, G::exp(old_state, pair.coefficient_b)));
+}
+
+/// Equivalent to witnesses::verify_non_mem_wit in an arbitrary group.
+pub fn verify_non_mem_wit<G: Group>(old_state: G::Elem, mut state: G::Elem, witness: (U2048, bool, G::Elem), elem: U2048) -> bool {
+    let (a, sign_a, B) = witness;
+    if sign_a {
+        state = G::inverse(state);
+    }
+    return G::op(G::exp(state, a), G::exp(B, elem)) == old_state;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::witnesses;
+
+    fn discriminant(f: Form) -> i128 {
+        let (a, b, c) = f;
+        return b * b - 4 * a * c;
+    }
+
+    #[test]
+    fn test_rsa_group() {
+        let modulus = U2048::from_dec_str(crate::MODULUS).unwrap();
+        assert_eq!(RsaGroup::exp(U2048::from(2), U2048::from(7)), U2048::from(11));
+        assert_eq!(RsaGroup::op(U2048::from(5), U2048::from(6)), U2048::from(4));
+        assert_eq!(RsaGroup::op(U2048::from(5), RsaGroup::inverse(U2048::from(5))), RsaGroup::identity());
+
+        let g = RsaGroup::hash_to_generator(b"generator");
+        assert_eq!(g >= U2048::from(2) && g < modulus, true);
+        assert_eq!(mem_wit_create::<RsaGroup>(g, U2048::from(15), U2048::from(3)), witnesses::mem_wit_create(g, U2048::from(15), U2048::from(3)));
+    }
+
+    #[test]
+    fn test_class_group_axioms() {
+        let g = ClassGroup::hash_to_generator(b"generator");
+        let h = ClassGroup::hash_to_generator(b"b");
+        let k = ClassGroup::hash_to_generator(b"c");
+        for &f in [g, h, k].iter() {
+            assert_eq!(discriminant(f), DISCRIMINANT);
+            assert_eq!(reduce(f), f);
+        }
+        assert_eq!(discriminant(ClassGroup::identity()), DISCRIMINANT);
+
+        let x = ClassGroup::exp(g, U2048::from_dec_str("123456789123456789123456789").unwrap());
+        let y = ClassGroup::exp(h, U2048::from_dec_str("987654321987654321").unwrap());
+        for &(a, b, c) in [(g, h, k), (x, y, g), (x, x, y)].iter() {
+            // Closure
+            assert_eq!(discriminant(ClassGroup::op(a, b)), DISCRIMINANT);
+            // Identity and inverses
+            assert_eq!(ClassGroup::op(a, ClassGroup::identity()), a);
+            assert_eq!(ClassGroup::op(a, ClassGroup::inverse(a)), ClassGroup::identity());
+            // Associativity and commutativity
+            assert_eq!(ClassGroup::op(ClassGroup::op(a, b), c), ClassGroup::op(a, ClassGroup::op(b, c)));
+            assert_eq!(ClassGroup::op(a, b), ClassGroup::op(b, a));
+        }
+
+        // Exponent laws
+        let (e_1, e_2) = (U2048::from(1234567), U2048::from(7654321));
+        assert_eq!(ClassGroup::exp(g, e_1 + e_2), ClassGroup::op(ClassGroup::exp(g, e_1), ClassGroup::exp(g, e_2)));
+        assert_eq!(ClassGroup::exp(ClassGroup::exp(g, e_1), e_2), ClassGroup::exp(g, e_1 * e_2));
+        assert_eq!(ClassGroup::exp(g, U2048::from(0)), ClassGroup::identity());
+    }
+
+    #[test]
+    fn test_class_group_witnesses() {
+        let g = ClassGroup::hash_to_generator(b"generator");
+        let elems = [subroutines::hash_to_prime(&[0]), subroutines::hash_to_prime(&[1])];
+        let product = elems[0] * elems[1];
+        let state = ClassGroup::exp(g, product);
+
+        let mem_wit = mem_wit_create::<ClassGroup>(g, product, elems[0]).unwrap();
+        assert_eq!(verify_mem_wit::<ClassGroup>(state, mem_wit, elems[0]), true);
+        assert_eq!(verify_mem_wit::<ClassGroup>(state, mem_wit, elems[1]), false);
+
+        let elem = subroutines::hash_to_prime(&[2]);
+        let non_mem_wit = non_mem_wit_create::<ClassGroup>(g, product, elem).unwrap();
+        assert_eq!(verify_non_mem_wit::<ClassGroup>(g, state, non_mem_wit, elem), true);
+        assert_eq!(non_mem_wit_create::<ClassGroup>(g, product, elems[1]), None);
+    }
+}
@@ -10,13 +10,31 @@ extern crate uint;
 pub mod subroutines;
 pub mod proofs;
 pub mod witnesses;
+pub mod wide;
+pub mod group;
 
-/// Construct BigInt type.
+/// Construct BigInt type. The SCALE encoding is a fixed-width 256 bytes(little endian limbs) and is
+/// what the Fiat-Shamir challenges hash. The wire types(Witness, vc::Commitment, vc::LightVerifier
+/// and proof::Proof) instead encode every U2048 as a 256 byte big endian integer(see encode_be).
 construct_uint! {
     #[derive(Encode, Decode)]
 	pub struct U2048(32);
 }
 
+/// Writes "elem" as a fixed-width 256 byte big endian integer.
+pub fn encode_be<W: codec::Output>(elem: &U2048, dest: &mut W) {
+    let mut bytes: [u8; 256] = [0; 256];
+    elem.to_big_endian(&mut bytes);
+    dest.write(&bytes);
+}
+
+/// Reads a U2048 written by encode_be.
+pub fn decode_be<I: codec::Input>(input: &mut I) -> Result<U2048, codec::Error> {
+    let mut bytes: [u8; 256] = [0; 256];
+    input.read(&mut bytes)?;
+    return Ok(U2048::from_big_endian(&bytes));
+}
+
 /// Defines the RSA group. Arbitrary set at MODULUS = 13 for testing.
 /// Example (insecure) modulus -> RSA 100: "1522605027922533360535618378132637429718068114961380688657908494580122963258952897654000350692006139"
 pub const MODULUS: &str = "13";
@@ -24,13 +42,183 @@ pub const MODULUS: &str = "13";
 /// Security parameter that represents the size of elements added to the accumulator.
 pub const LAMBDA: u32 = u32::max_value();
 
+/// Serializes as a "0x" prefixed lowercase hex string without leading zeros.
+#[cfg(feature = "serde")]
+impl serde::Serialize for U2048 {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut bytes: [u8; 256] = [0; 256];
+        self.to_big_endian(&mut bytes);
+        let digits: alloc::string::String = bytes.iter().map(|byte| alloc::format!("{:02x}", byte)).collect();
+        let trimmed = digits.trim_start_matches('0');
+        let hex = if trimmed.is_empty() { "0" } else { trimmed };
+        return serializer.serialize_str(&alloc::format!("0x{}", hex));
+    }
+}
+
+/// Deserializes from a hex string with an optional "0x" prefix.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for U2048 {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let string = <alloc::string::String as serde::Deserialize>::deserialize(deserializer)?;
+        let digits = string.trim_start_matches("0x");
+        if digits.is_empty() || digits.len() > 512 {
+            return Err(serde::de::Error::custom("invalid U2048 hex string"));
+        }
+
+        let mut result = U2048::from(0);
+        for c in digits.chars() {
+            let digit = c.to_digit(16).ok_or_else(|| serde::de::Error::custom("invalid U2048 hex string"))?;
+            result = result * U2048::from(16) + U2048::from(digit);
+        }
+        return Ok(result);
+    }
+}
+
 /// A witness can either be a membership witness or a non-membership witness.
-#[derive(Encode, Decode, Clone, Copy, Eq, PartialEq, Debug)]
+/// The SCALE encoding is a variant byte(0 = MemWit, 1 = NonMemWit) followed by the fields, with each
+/// U2048 in big endian(see encode_be) and the sign as a single byte.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Eq, PartialEq, Hash)]
 pub enum Witness {
     MemWit(U2048),
     NonMemWit((U2048,bool, U2048)),
 }
 
+impl Witness {
+    /// Checks that the group elements of the witness are canonical residues in [0, modulus). Should
+    /// be called on witnesses decoded from untrusted bytes since mod_exp silently reduces its base.
+    /// NOTE: The Bezout coefficient of a non-membership witness is an exponent and is not checked.
+    pub fn validate(&self, modulus: U2048) -> bool {
+        match self {
+            Witness::MemWit(witness) => {
+                return *witness < modulus;
+            },
+            Witness::NonMemWit((_, _, B)) => {
+                return *B < modulus;
+            },
+        }
+    }
+
+    /// Reduces the group elements of the witness to their canonical residues mod "modulus" in place so
+    /// that equal witnesses built by different code paths share one representation. A zero Bezout
+    /// coefficient is also made positive.
+    /// NOTE: The Bezout coefficient is an exponent and is not reduced since the order of the group is
+    /// unknown.
+    pub fn normalize(&mut self, modulus: U2048) {
+        match self {
+            Witness::MemWit(witness) => {
+                *witness = *witness % modulus;
+            },
+            Witness::NonMemWit((a, sign_a, B)) => {
+                if a.is_zero() {
+                    *sign_a = false;
+                }
+                *B = *B % modulus;
+            },
+        }
+    }
+
+    /// Compares the canonical forms of two witnesses(see normalize), so that a witness matches the
+    /// same witness with an unreduced group element(Ex: MemWit(5) and MemWit(18) for modulus 13).
+    /// Unlike ==, which compares the exact representation(and encoding).
+    pub fn eq_canonical(&self, other: &Self, modulus: U2048) -> bool {
+        let (mut lhs, mut rhs) = (*self, *other);
+        lhs.normalize(modulus);
+        rhs.normalize(modulus);
+        return lhs == rhs;
+    }
+}
+
+/// Prints the components in hex. The Bezout coefficient of a non-membership witness is signed.
+/// Ex: MemWit(0xb) or NonMemWit(-0x3, 0x5).
+impl core::fmt::Display for Witness {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Witness::MemWit(witness) => {
+                write!(f, "MemWit(")?;
+                write_hex(f, witness)?;
+            },
+            Witness::NonMemWit((a, sign_a, B)) => {
+                write!(f, "NonMemWit({}", if *sign_a { "-" } else { "" })?;
+                write_hex(f, a)?;
+                write!(f, ", ")?;
+                write_hex(f, B)?;
+            },
+        }
+        return write!(f, ")");
+    }
+}
+
+/// Writes a U2048 as 0x-prefixed hex without leading zeros.
+fn write_hex(f: &mut core::fmt::Formatter, value: &U2048) -> core::fmt::Result {
+    // Print zero explicitly since LowerHex omits every leading zero nibble
+    if value.is_zero() {
+        return write!(f, "0x0");
+    }
+    return write!(f, "0x{:x}", value);
+}
+
+/// Prints a fingerprint(the first 8 hex characters of the blake2 hash of the SCALE encoding) rather
+/// than the full 2048-bit components so that witnesses can be compared across log lines.
+impl core::fmt::Debug for Witness {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let hash = runtime_io::blake2_256(&self.encode());
+        let variant = match self {
+            Witness::MemWit(_) => "MemWit",
+            Witness::NonMemWit(_) => "NonMemWit",
+        };
+        return write!(f, "{}#{:02x}{:02x}{:02x}{:02x}", variant, hash[0], hash[1], hash[2], hash[3]);
+    }
+}
+
+impl Encode for Witness {
+    fn size_hint(&self) -> usize {
+        match self {
+            Witness::MemWit(_) => return 257,
+            Witness::NonMemWit(_) => return 514,
+        }
+    }
+
+    fn encode_to<W: codec::Output>(&self, dest: &mut W) {
+        match self {
+            Witness::MemWit(witness) => {
+                dest.write(&[0]);
+                encode_be(witness, dest);
+            },
+            Witness::NonMemWit((a, sign_a, B)) => {
+                dest.write(&[1]);
+                encode_be(a, dest);
+                dest.write(&[*sign_a as u8]);
+                encode_be(B, dest);
+            },
+        }
+    }
+}
+
+impl Decode for Witness {
+    fn decode<I: codec::Input>(input: &mut I) -> Result<Self, codec::Error> {
+        let mut byte: [u8; 1] = [0; 1];
+        input.read(&mut byte)?;
+        match byte[0] {
+            0 => {
+                return Ok(Witness::MemWit(decode_be(input)?));
+            },
+            1 => {
+                let a = decode_be(input)?;
+                input.read(&mut byte)?;
+                if byte[0] > 1 {
+                    return Err("Invalid sign byte".into());
+                }
+                let B = decode_be(input)?;
+                return Ok(Witness::NonMemWit((a, byte[0] == 1, B)));
+            },
+            _ => {
+                return Err("Invalid witness variant".into());
+            },
+        }
+    }
+}
+
 // Cannot derive the Default trait for Enums so this is the only option
 impl Default for Witness {
     fn default() -> Self {
@@ -42,10 +230,10 @@ impl Default for Witness {
 /// signed BigInts(coefficients can be negative).
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub struct BezoutPair {
-    coefficient_a: U2048,
-    coefficient_b: U2048,
-    sign_a: bool, // True indicates negative and false indicates positive
-    sign_b: bool,
+    pub coefficient_a: U2048,
+    pub coefficient_b: U2048,
+    pub sign_a: bool, // True indicates negative and false indicates positive
+    pub sign_b: bool,
 }
 
 /// Add a single element to an accumulator.
@@ -74,8 +262,34 @@ pub fn batch_delete(state: U2048, elems: &Vec<(U2048, U2048)>) -> (U2048, U2048,
     return (new_state, x_agg, proof);
 }
 
+/// Equivalent to batch_delete but verifies the membership witness of every element against "state"
+/// first. Returns None if an element is not contained in the accumulator(Ex: it was never added or
+/// was already deleted) or if the elements cannot be aggregated. Deleting no elements leaves the
+/// state unchanged.
+/// NOTE: Verifiers check the returned proof with proofs::verify_poe(new_state, product, state, proof).
+pub fn checked_batch_delete(state: U2048, elems: &Vec<(U2048, U2048)>) -> Option<(U2048, U2048, U2048)> {
+    if elems.iter().any(|(x, witness)| !witnesses::verify_mem_wit(state, *witness, *x)) {
+        return None;
+    }
+
+    let mut x_agg = U2048::from(1);
+    let mut new_state = state;
+    for (i, &(x, witness)) in elems.iter().enumerate() {
+        if i == 0 {
+            new_state = witness;
+        }
+        else {
+            new_state = subroutines::shamir_trick(new_state, witness, x_agg, x)?;
+        }
+        x_agg *= x;
+    }
+    let proof = proofs::poe(new_state, x_agg, state);
+    return Some((new_state, x_agg, proof));
+}
+
 /// Aggregates a set of accumulator elements + witnesses and batch adds them to the accumulator.
 /// Returns the state after addition, the product of the added elements, and a proof of exponentiation.
+/// NOTE: Verifiers check the returned proof with proofs::verify_poe(state, product, new_state, proof).
 pub fn batch_add(state: U2048, elems: &Vec<U2048>) -> (U2048, U2048, U2048) {
     let mut x_agg = U2048::from(1);
     for i in 0..elems.len() {
@@ -86,3 +300,163 @@ pub fn batch_add(state: U2048, elems: &Vec<U2048>) -> (U2048, U2048, U2048) {
     let proof = proofs::poe(state, x_agg, new_state);
     return (new_state, x_agg, proof);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_witness_normalize() {
+        let modulus = U2048::from_dec_str(MODULUS).unwrap();
+
+        // The same membership witness as a canonical residue and reduced from a different path
+        let canonical = Witness::MemWit(U2048::from(5));
+        let mut unreduced = Witness::MemWit(U2048::from(5) + modulus);
+        assert_ne!(unreduced, canonical);
+        assert_eq!(unreduced.eq_canonical(&canonical, modulus), true);
+        unreduced.normalize(modulus);
+        assert_eq!(unreduced, canonical);
+        assert_eq!(unreduced.encode(), canonical.encode());
+
+        let mut non_mem_wit = Witness::NonMemWit((U2048::from(0), true, U2048::from(31)));
+        assert_eq!(non_mem_wit.eq_canonical(&Witness::NonMemWit((U2048::from(0), false, U2048::from(5))), modulus), true);
+        non_mem_wit.normalize(modulus);
+        assert_eq!(non_mem_wit, Witness::NonMemWit((U2048::from(0), false, U2048::from(5))));
+        let non_mem_wit = Witness::NonMemWit((U2048::from(3), true, U2048::from(5)));
+        assert_eq!(non_mem_wit.eq_canonical(&Witness::NonMemWit((U2048::from(3), false, U2048::from(5))), modulus), false);
+        assert_eq!(non_mem_wit.eq_canonical(&Witness::NonMemWit((U2048::from(16), true, U2048::from(5))), modulus), false);
+        assert_eq!(canonical.eq_canonical(&Witness::NonMemWit((U2048::from(0), false, U2048::from(5))), modulus), false);
+
+        // Witnesses under a different modulus are only reduced by that modulus
+        let (a, b) = (Witness::MemWit(U2048::from(18)), Witness::MemWit(U2048::from(5)));
+        assert_eq!(a.eq_canonical(&b, U2048::from(3233)), false);
+        assert_eq!(a.eq_canonical(&b, modulus), true);
+    }
+
+    #[test]
+    fn test_batch_add_and_delete() {
+        let elems: Vec<U2048> = (0..4u8).map(|i| subroutines::hash_to_prime(&[i])).collect();
+        let (state, product, proof) = batch_add(U2048::from(2), &elems[..3].to_vec());
+        assert_eq!(state, add(add(add(U2048::from(2), elems[0]), elems[1]), elems[2]));
+        assert_eq!(proofs::verify_poe(U2048::from(2), product, state, proof), true);
+
+        let deletions: Vec<(U2048, U2048)> = elems[..2].iter()
+            .map(|elem| (*elem, witnesses::mem_wit_create(U2048::from(2), product, *elem).unwrap()))
+            .collect();
+        let (new_state, agg, proof) = checked_batch_delete(state, &deletions).unwrap();
+        assert_eq!(new_state, add(U2048::from(2), elems[2]));
+        assert_eq!(agg, elems[0] * elems[1]);
+        assert_eq!(proofs::verify_poe(new_state, agg, state, proof), true);
+        assert_eq!(batch_delete(state, &deletions), (new_state, agg, proof));
+        assert_eq!(checked_batch_delete(state, &vec![]), Some((state, U2048::from(1), proofs::poe(state, U2048::from(1), state))));
+
+        // Deleting an element that is not contained in the accumulator
+        let absent = vec![deletions[0], (elems[3], deletions[1].1)];
+        assert_eq!(checked_batch_delete(state, &absent), None);
+    }
+
+    #[test]
+    fn test_codec() {
+        let elems = vec![subroutines::hash_to_prime(&[0]), subroutines::hash_to_prime(&[1])];
+        let (state, product, _) = batch_add(U2048::from(2), &elems);
+        assert_eq!(state.encode().len(), 256);
+        assert_eq!(U2048::max_value().encode().len(), 256);
+        assert_eq!(U2048::decode(&mut &state.encode()[..]).unwrap(), state);
+        assert_eq!(<(U2048, U2048)>::decode(&mut &(state, product).encode()[..]).unwrap(), (state, product));
+
+        // Variant index + fixed-width fields
+        let mem_wit = Witness::MemWit(witnesses::mem_wit_create(U2048::from(2), product, elems[0]).unwrap());
+        let non_mem_wit = Witness::NonMemWit(witnesses::non_mem_wit_create(U2048::from(2), product, subroutines::hash_to_prime(&[2])));
+        assert_eq!(mem_wit.encode().len(), 257);
+        assert_eq!(non_mem_wit.encode().len(), 514);
+        assert_eq!(Witness::decode(&mut &mem_wit.encode()[..]).unwrap(), mem_wit);
+        assert_eq!(Witness::decode(&mut &non_mem_wit.encode()[..]).unwrap(), non_mem_wit);
+        assert_eq!(U2048::decode(&mut &[0u8; 255][..]).is_err(), true);
+
+        // The U2048 fields of a witness are big endian
+        let mut expected = vec![0u8; 514];
+        expected[0] = 1;
+        expected[256] = 0x01;
+        expected[257] = 1;
+        expected[513] = 0x02;
+        let witness = Witness::NonMemWit((U2048::from(1), true, U2048::from(2)));
+        assert_eq!(witness.encode(), expected);
+        assert_eq!(Witness::decode(&mut &expected[..]).unwrap(), witness);
+        assert_eq!(Witness::decode(&mut &expected[..513]).is_err(), true);
+
+        // Unknown variant and bad sign byte
+        let mut tampered = expected.clone();
+        tampered[0] = 2;
+        assert_eq!(Witness::decode(&mut &tampered[..]).is_err(), true);
+        let mut tampered = expected.clone();
+        tampered[257] = 2;
+        assert_eq!(Witness::decode(&mut &tampered[..]).is_err(), true);
+    }
+
+    #[test]
+    fn test_witness_validate() {
+        let modulus = U2048::from_dec_str(MODULUS).unwrap();
+        assert_eq!(Witness::MemWit(U2048::from(12)).validate(modulus), true);
+        assert_eq!(Witness::MemWit(U2048::from(13)).validate(modulus), false);
+        assert_eq!(Witness::MemWit(U2048::max_value()).validate(modulus), false);
+
+        // The Bezout coefficient may exceed the modulus
+        assert_eq!(Witness::NonMemWit((U2048::from(1000), true, U2048::from(5))).validate(modulus), true);
+        assert_eq!(Witness::NonMemWit((U2048::from(1), false, U2048::from(18))).validate(modulus), false);
+    }
+
+    #[test]
+    fn test_witness_fmt() {
+        let mem_wit = Witness::MemWit(U2048::from(11));
+        let non_mem_wit = Witness::NonMemWit((U2048::from(3), true, U2048::from(5)));
+        assert_eq!(format!("{}", mem_wit), "MemWit(0xb)");
+        assert_eq!(format!("{}", non_mem_wit), "NonMemWit(-0x3, 0x5)");
+        assert_eq!(format!("{}", Witness::NonMemWit((U2048::from(255), false, U2048::max_value()))),
+                   format!("NonMemWit(0xff, 0x{})", "f".repeat(512)));
+
+        assert_eq!(format!("{}", Witness::default()), "MemWit(0x0)");
+
+        assert_eq!(format!("{:?}", mem_wit), "MemWit#7655aee9");
+        assert_eq!(format!("{:?}", non_mem_wit), "NonMemWit#3bcc4351");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_u2048() {
+        assert_eq!(serde_json::to_string(&U2048::from(0)).unwrap(), "\"0x0\"");
+        assert_eq!(serde_json::to_string(&U2048::from(255)).unwrap(), "\"0xff\"");
+
+        let modulus = U2048::from_dec_str(MODULUS).unwrap();
+        for &elem in [U2048::from(0), U2048::from(1), modulus, U2048::max_value()].iter() {
+            let json = serde_json::to_string(&elem).unwrap();
+            assert_eq!(serde_json::from_str::<U2048>(&json).unwrap(), elem);
+        }
+
+        assert_eq!(serde_json::from_str::<U2048>("\"ff\"").unwrap(), U2048::from(255));
+        assert_eq!(serde_json::from_str::<U2048>("\"0xzz\"").is_err(), true);
+        assert_eq!(serde_json::from_str::<U2048>("\"0x\"").is_err(), true);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_witness() {
+        let witnesses = [Witness::MemWit(U2048::from(12)), Witness::NonMemWit((U2048::from(3), true, U2048::max_value()))];
+        for witness in witnesses.iter() {
+            let json = serde_json::to_string(witness).unwrap();
+            assert_eq!(serde_json::from_str::<Witness>(&json).unwrap(), *witness);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_committed_state() {
+        let elems = vec![subroutines::hash_to_prime(&[0]), subroutines::hash_to_prime(&[1])];
+        let (state, product, _) = batch_add(U2048::from(2), &elems);
+        let mem_wit = Witness::MemWit(witnesses::mem_wit_create(U2048::from(2), product, elems[0]).unwrap());
+        let non_mem_wit = Witness::NonMemWit(witnesses::non_mem_wit_create(U2048::from(2), product, subroutines::hash_to_prime(&[2])));
+
+        let json = serde_json::to_string(&(state, product, mem_wit, non_mem_wit)).unwrap();
+        let decoded: (U2048, U2048, Witness, Witness) = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, (state, product, mem_wit, non_mem_wit));
+    }
+}
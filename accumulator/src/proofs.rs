@@ -5,17 +5,20 @@ use codec::{Encode};
 use crate::subroutines;
 use super::U2048;
 
+/// Wesolowski proof of exponentiation: Q = u^floor(x / l) for the challenge prime l.
+pub type PoeProof = U2048;
+
 /// Generates proof of exponentiation that u^x = w (based on Wesolowski). Protocol is only useful
 /// if the verifier can compute the residue r = x mod l faster than computing u^x.
 /// To investigate: Security parameter should be larger than that of accumulator elements.
-pub fn poe(u: U2048, x: U2048, w: U2048) -> U2048 {
+pub fn poe(u: U2048, x: U2048, w: U2048) -> PoeProof {
     let l = subroutines::hash_to_prime(&(u, x, w).encode());
     let q = x / l;
     return subroutines::mod_exp(u, q, U2048::from_dec_str(super::MODULUS).unwrap());
 }
 
 /// Verifies proof of exponentiation.
-pub fn verify_poe(u: U2048, x: U2048, w: U2048, Q: U2048) -> bool {
+pub fn verify_poe(u: U2048, x: U2048, w: U2048, Q: PoeProof) -> bool {
     let l = subroutines::hash_to_prime(&(u, x, w).encode());
     let r = x % l;
     let lhs = subroutines::mul_mod(subroutines::mod_exp(Q, l, U2048::from_dec_str(super::MODULUS).unwrap()), subroutines::mod_exp(u, r, U2048::from_dec_str(super::MODULUS).unwrap()),
@@ -23,10 +26,14 @@ pub fn verify_poe(u: U2048, x: U2048, w: U2048, Q: U2048) -> bool {
     return lhs == w;
 }
 
-/// Generates proof of knowledge of exponentiation that u^x = w. We will assume that the generator
-/// g = 2 is a group element of unknown order.
+/// Proof of knowledge of exponentiation represented as (z, Q, r).
+pub type PokeProof = (U2048, U2048, U2048);
+
+/// Generates proof of knowledge of exponentiation that u^x = w (NI-PoKE2 from section 3.3 of
+/// https://eprint.iacr.org/2018/1188.pdf). The proof consists of z = g^x, the quotient Q and the
+/// residue r = x mod l. We will assume that the generator g = 2 is a group element of unknown order.
 /// To investigate: Security parameter should be larger than that of accumulator elements.
-pub fn poke(u: U2048, x: U2048, w: U2048) -> (U2048, U2048, U2048) {
+pub fn poke(u: U2048, x: U2048, w: U2048) -> PokeProof {
     let z = subroutines::mod_exp(U2048::from(2), x, U2048::from_dec_str(super::MODULUS).unwrap());
     let l = subroutines::hash_to_prime(&(u, w, z).encode());
     let alpha = U2048::from_little_endian(&blake2_256(&(u, w, z, l).encode()));
@@ -61,6 +68,13 @@ mod tests {
         proof = poe(U2048::from(121314), U2048::from(14123), U2048::from(6));
         assert_eq!(verify_poe(U2048::from(121314), U2048::from(14123), U2048::from(6), proof), true);
 
+        // Accumulating two elements
+        let x = subroutines::hash_to_prime(&[0]) * subroutines::hash_to_prime(&[1]);
+        let w = subroutines::mod_exp(U2048::from(2), x, U2048::from_dec_str(crate::MODULUS).unwrap());
+        proof = poe(U2048::from(2), x, w);
+        assert_eq!(verify_poe(U2048::from(2), x, w, proof), true);
+        assert_eq!(verify_poe(U2048::from(2), x, w + U2048::from(1), proof), false);
+
         // Fake proof
         assert_eq!(verify_poe(U2048::from(2), U2048::from(6), U2048::from(12), U2048::from(3)), false);
         assert_eq!(verify_poe(U2048::from(4), U2048::from(12), U2048::from(7), U2048::from(1)), false);
@@ -74,6 +88,12 @@ mod tests {
         let (z, Q, r) = poke(U2048::from(121314), U2048::from(14123), U2048::from(6));
         assert_eq!(verify_poke(U2048::from(121314), U2048::from(6), z, Q, r), true);
 
+        // Proofs generated from the wrong exponent
+        let (z_forged, Q_forged, r_forged) = poke(U2048::from(2), U2048::from(5), U2048::from(12));
+        assert_eq!(verify_poke(U2048::from(2), U2048::from(12), z_forged, Q_forged, r_forged), false);
+        let (z_forged, Q_forged, r_forged) = poke(U2048::from(121314), U2048::from(14124), U2048::from(6));
+        assert_eq!(verify_poke(U2048::from(121314), U2048::from(6), z_forged, Q_forged, r_forged), false);
+
         // Fake proof
         assert_eq!(verify_poke(U2048::from(121314), U2048::from(7), z, Q, r), false);
         assert_eq!(verify_poke(U2048::from(2), U2048::from(12), U2048::from(4), U2048::from(1), U2048::from(2)), false);
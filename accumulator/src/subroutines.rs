@@ -1,9 +1,12 @@
 /// Integer Subroutines for Accumulator Functions.
 
-use runtime_io::blake2_256;
+use runtime_io::{blake2_256, keccak_256};
+use sha2::{Digest, Sha256};
 use rstd::prelude::Vec;
 use super::U2048;
 use crate::BezoutPair;
+#[cfg(feature = "std")]
+use rand::RngCore;
 
 /// Implements fast modular exponentiation. Algorithm inspired by https://github.com/pwoolcoc/mod_exp-rs/blob/master/src/lib.rs
 /// NOTE: Possible overflow error occurs when size of result exceeds U2048.
@@ -25,6 +28,190 @@ pub fn mod_exp(mut base: U2048, mut exp: U2048, modulus: U2048) -> U2048 {
     return result;
 }
 
+/// Equivalent to mod_exp but scans the exponent in sliding windows of up to 4 bits using a
+/// precomputed table of odd powers of the base. This reduces the number of multiplications
+/// for large exponents.
+pub fn mod_exp_windowed(base: U2048, exp: U2048, modulus: U2048) -> U2048 {
+    return mod_exp_window_size(base, exp, modulus, 4);
+}
+
+/// Sliding-window exponentiation with a tunable window size. Larger windows need fewer
+/// multiplications while scanning but a table of 2^(window_size - 1) precomputed powers.
+/// NOTE: Panics if window_size is 0.
+pub fn mod_exp_window_size(mut base: U2048, exp: U2048, modulus: U2048, window_size: usize) -> U2048 {
+    assert!(window_size > 0);
+    if exp == U2048::from(0) {
+        return U2048::from(1);
+    }
+
+    // table[k] = base^(2k + 1)
+    base = base % modulus;
+    let base_squared = mul_mod(base, base, modulus);
+    let mut table: Vec<U2048> = Vec::with_capacity(1 << (window_size - 1));
+    table.push(base);
+    for k in 1..(1 << (window_size - 1)) {
+        let prev = table[k - 1];
+        table.push(mul_mod(prev, base_squared, modulus));
+    }
+
+    let mut result: U2048 = U2048::from(1);
+    let mut i = exp.bits();
+    while i > 0 {
+        if !exp.bit(i - 1) {
+            result = mul_mod(result, result, modulus);
+            i -= 1;
+            continue;
+        }
+
+        // Find the longest window ending in a set bit
+        let mut j = if i > window_size { i - window_size } else { 0 };
+        while !exp.bit(j) {
+            j += 1;
+        }
+
+        let mut window = 0;
+        for k in (j..i).rev() {
+            result = mul_mod(result, result, modulus);
+            window = (window << 1) | (exp.bit(k) as usize);
+        }
+        result = mul_mod(result, table[window >> 1], modulus);
+        i = j;
+    }
+    return result;
+}
+
+/// Computes prod(base_i^exp_i) mod MODULUS. Equivalent to multiplying the results of mod_exp for every
+/// pair but faster(see multi_exp_with_modulus).
+pub fn multi_exp(pairs: &[(U2048, U2048)]) -> U2048 {
+    return multi_exp_with_modulus(pairs, U2048::from_dec_str(super::MODULUS).unwrap());
+}
+
+/// Equivalent to multi_exp for an arbitrary modulus. Uses simultaneous(Straus) exponentiation: the
+/// bits of every exponent are scanned together from the most significant bit, so a single chain of
+/// squarings is shared by all of the bases instead of one chain per base.
+pub fn multi_exp_with_modulus(pairs: &[(U2048, U2048)], modulus: U2048) -> U2048 {
+    let bases: Vec<U2048> = pairs.iter().map(|(base, _)| *base % modulus).collect();
+    let bits = pairs.iter().map(|(_, exp)| exp.bits()).max().unwrap_or(0);
+
+    let mut result: U2048 = U2048::from(1);
+    for i in (0..bits).rev() {
+        result = mul_mod(result, result, modulus);
+        for (j, (_, exp)) in pairs.iter().enumerate() {
+            if exp.bit(i) {
+                result = mul_mod(result, bases[j], modulus);
+            }
+        }
+    }
+    return result;
+}
+
+/// A base with a precomputed table of powers, which amortizes the cost of many exponentiations that
+/// share the base(Ex: the generator U2048::from(2) or the current state). The table holds
+/// base^(d * 2^(window * i)) for every window i of a 2048 bit exponent and every digit d < 2^window,
+/// so exp needs one multiplication per non-zero window of the exponent and no squarings.
+/// NOTE: The table holds ceil(2048 / window) * 2^window elements, so large windows use a lot of memory.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PrecomputedBase {
+    modulus: U2048,
+    window: usize,
+    table: Vec<U2048>,
+}
+
+impl PrecomputedBase {
+    /// NOTE: Panics if window is 0 or greater than 16.
+    pub fn new(base: U2048, modulus: U2048, window: usize) -> Self {
+        assert!((1..=16).contains(&window));
+        let digits = 1 << window;
+        let windows = 2048 / window + (2048 % window != 0) as usize;
+        let mut table: Vec<U2048> = Vec::with_capacity(windows * digits);
+
+        // power = base^(2^(window * i))
+        let mut power = base % modulus;
+        for _ in 0..windows {
+            let mut elem = U2048::from(1);
+            for _ in 0..digits {
+                table.push(elem);
+                elem = mul_mod(elem, power, modulus);
+            }
+            power = elem;
+        }
+        return PrecomputedBase { modulus, window, table };
+    }
+
+    /// Equivalent to mod_exp(base, e, modulus).
+    pub fn exp(&self, e: U2048) -> U2048 {
+        let digits = 1 << self.window;
+        let mut result: U2048 = U2048::from(1);
+        let mut i = 0;
+        while i * self.window < e.bits() {
+            let mut digit = 0;
+            for k in (i * self.window..(i + 1) * self.window).rev() {
+                digit = (digit << 1) | ((k < 2048 && e.bit(k)) as usize);
+            }
+            if digit != 0 {
+                result = mul_mod(result, self.table[i * digits + digit], self.modulus);
+            }
+            i += 1;
+        }
+        return result;
+    }
+}
+
+/// Equivalent to mod_exp but uses a Montgomery ladder over all 2048 bits of the exponent so that
+/// the sequence of group operations does not depend on the exponent. Intended for secret exponents
+/// (Ex: witnesses, see witnesses::mem_wit_create_ct) while mod_exp remains the fast path. The multiplications use
+/// mod_mul_ct so that neither the branches nor the iteration counts depend on the exponent.
+/// NOTE: Only the exponent is treated as secret. The base is reduced with a variable-time "%".
+pub fn mod_exp_ct(base: U2048, exp: U2048, modulus: U2048) -> U2048 {
+    let mut r0: U2048 = U2048::from(1);
+    let mut r1: U2048 = base % modulus;
+    for i in (0..2048).rev() {
+        let bit = exp.bit(i);
+        conditional_swap(&mut r0, &mut r1, bit);
+        r1 = mod_mul_ct(r0, r1, modulus);
+        r0 = mod_mul_ct(r0, r0, modulus);
+        conditional_swap(&mut r0, &mut r1, bit);
+    }
+    return r0;
+}
+
+/// Equivalent to mul_mod but without data-dependent branches. Runs one double-and-add step per bit
+/// of the (public) modulus and reduces with conditional subtractions instead of "%".
+/// NOTE: "a" and "b" must already be reduced mod "modulus".
+pub fn mod_mul_ct(a: U2048, b: U2048, modulus: U2048) -> U2048 {
+    let mut result = U2048::from(0);
+    for i in (0..modulus.bits()).rev() {
+        result = mod_add_ct(result, result, modulus);
+        let sum = mod_add_ct(result, a, modulus);
+        result = conditional_select(result, sum, b.bit(i));
+    }
+    return result;
+}
+
+/// Computes (a + b) mod modulus for reduced "a" and "b". The overflow flag accounts for moduli
+/// with the top bit set.
+fn mod_add_ct(a: U2048, b: U2048, modulus: U2048) -> U2048 {
+    let (sum, overflow) = a.overflowing_add(b);
+    let (diff, borrow) = sum.overflowing_sub(modulus);
+    return conditional_select(sum, diff, overflow | !borrow);
+}
+
+/// Returns b if "choice" is true and a otherwise without branching on "choice".
+fn conditional_select(mut a: U2048, mut b: U2048, choice: bool) -> U2048 {
+    conditional_swap(&mut a, &mut b, choice);
+    return a;
+}
+
+/// Swaps a and b if "swap" is true without branching on "swap".
+fn conditional_swap(a: &mut U2048, b: &mut U2048, swap: bool) {
+    let mask = 0u64.wrapping_sub(swap as u64);
+    for i in 0..a.0.len() {
+        let t = mask & (a.0[i] ^ b.0[i]);
+        a.0[i] ^= t;
+        b.0[i] ^= t;
+    }
+}
+
 /// Defines the multiplication operation for the group. Idea courtesy of:
 /// https://www.geeksforgeeks.org/how-to-avoid-overflow-in-modular-multiplication/
 pub fn mul_mod(mut a: U2048, mut b: U2048, modulus: U2048) -> U2048 {
@@ -44,50 +231,66 @@ pub fn mul_mod(mut a: U2048, mut b: U2048, modulus: U2048) -> U2048 {
 /// Given the xth root of g and yth root of g, finds the xyth root. If the roots are invalid or
 /// x and y are not coprime, None is returned. Otherwise, the function performs relevant modular
 /// inverse operations on the Bezout coefficients and finds the xyth root.
-pub fn shamir_trick(mut xth_root: U2048, mut yth_root: U2048, x: U2048, y: U2048) -> Option<U2048> {
+pub fn shamir_trick(xth_root: U2048, yth_root: U2048, x: U2048, y: U2048) -> Option<U2048> {
+    return shamir_trick_with_modulus(xth_root, yth_root, x, y, U2048::from_dec_str(super::MODULUS).unwrap());
+}
+
+/// Equivalent to shamir_trick for an arbitrary modulus. Given w_1 = g^(1/x) and w_2 = g^(1/y),
+/// returns g^(1/xy) = w_1^b * w_2^a where ax + by = 1.
+/// NOTE: Requires gcd(x, y) = 1 as integers(Ex: distinct primes), otherwise no such a and b exist
+/// and None is returned. Also returns None if w_1^x != w_2^y, since then the roots are not of the
+/// same element. Coprimality with the group order is not required.
+pub fn shamir_trick_with_modulus(mut xth_root: U2048, mut yth_root: U2048, x: U2048, y: U2048, modulus: U2048) -> Option<U2048> {
     // Check if the inputs are valid.
-    if mod_exp(xth_root, x, U2048::from_dec_str(super::MODULUS).unwrap())
-        != mod_exp(yth_root, y, U2048::from_dec_str(super::MODULUS).unwrap()) {
+    if mod_exp(xth_root, x, modulus) != mod_exp(yth_root, y, modulus) {
         return None;
     }
 
-    match bezout(x, y) {
-        None => {
-            return None;
-        },
-        Some(coefficients) => {
-            // Receive coefficient
-            let pair = coefficients;
-
-            // Calculate relevant modular inverses to allow for exponentiation later on.
-            if pair.sign_b {
-                xth_root = mod_inverse(xth_root);
-            }
-
-            if pair.sign_a {
-                yth_root = mod_inverse(yth_root);
-            }
+    let pair = bezout(x, y)?;
 
-            let combined_root: U2048 = (mod_exp(xth_root, U2048::from(pair.coefficient_b), U2048::from_dec_str(super::MODULUS).unwrap())
-                * mod_exp(yth_root, U2048::from(pair.coefficient_a), U2048::from_dec_str(super::MODULUS).unwrap())) % U2048::from_dec_str(super::MODULUS).unwrap();
-            return Some(combined_root);
-        },
+    // Calculate relevant modular inverses to allow for exponentiation later on.
+    if pair.sign_b {
+        xth_root = mod_inverse_with_modulus(xth_root, modulus);
+    }
+    if pair.sign_a {
+        yth_root = mod_inverse_with_modulus(yth_root, modulus);
     }
+
+    let combined_root = mul_mod(mod_exp(xth_root, pair.coefficient_b, modulus), mod_exp(yth_root, pair.coefficient_a, modulus), modulus);
+    return Some(combined_root);
 }
 
 /// Computes the modular multiplicative inverse.
 /// NOTE: Does not check if gcd != 1(none exists if so).
 pub fn mod_inverse(elem: U2048) -> U2048 {
-    let (_, pair) = extended_gcd(elem, U2048::from_dec_str(super::MODULUS).unwrap());
+    return mod_inverse_with_modulus(elem, U2048::from_dec_str(super::MODULUS).unwrap());
+}
+
+/// Equivalent to mod_inverse for an arbitrary modulus.
+pub fn mod_inverse_with_modulus(elem: U2048, modulus: U2048) -> U2048 {
+    let (_, pair) = extended_gcd(elem, modulus);
 
     // Accommodate for negative x coefficient
     if pair.sign_a {
-        // Since we're assuming that the U2048::from(super::MODULUS) will always be larger than than coefficient in
-        // absolute value, we simply subtract x from the U2048::from(super::MODULUS) to get a positive value mod N.
-        let pos_a = U2048::from_dec_str(super::MODULUS).unwrap() - pair.coefficient_a;
-        return pos_a % U2048::from_dec_str(super::MODULUS).unwrap();
+        // Since we're assuming that the modulus will always be larger than than coefficient in
+        // absolute value, we simply subtract x from the modulus to get a positive value mod N.
+        let pos_a = modulus - pair.coefficient_a;
+        return pos_a % modulus;
+    }
+    return U2048::from(pair.coefficient_a) % modulus;
+}
+
+/// Equivalent to mod_inverse_with_modulus but returns None if "elem" is not invertible(shares a
+/// factor with "modulus").
+pub fn checked_mod_inverse(elem: U2048, modulus: U2048) -> Option<U2048> {
+    let (gcd, (x, negative), _) = xgcd(elem % modulus, modulus);
+    if gcd != U2048::from(1) {
+        return None;
     }
-    return U2048::from(pair.coefficient_a) % U2048::from_dec_str(super::MODULUS).unwrap();
+    if negative {
+        return Some((modulus - x) % modulus);
+    }
+    return Some(x % modulus);
 }
 
 /// Returns Bezout coefficients. Acts as a wrapper for extended_gcd.
@@ -102,6 +305,13 @@ pub fn bezout(a: U2048, b: U2048) -> Option<BezoutPair> {
     }
 }
 
+/// Returns (gcd, x, y) such that a*x + b*y = gcd. Each coefficient is represented as
+/// (|coefficient|, is_negative). Acts as a wrapper for extended_gcd.
+pub fn xgcd(a: U2048, b: U2048) -> (U2048, (U2048, bool), (U2048, bool)) {
+    let (gcd, pair) = extended_gcd(a, b);
+    return (gcd, (pair.coefficient_a, pair.sign_a), (pair.coefficient_b, pair.sign_b));
+}
+
 /// Implements the Extended Euclidean Algorithm (https://en.wikipedia.org/wiki/Extended_Euclidean_algorithm).
 /// IMPORTANT NOTE: Instead of representing the coefficients as signed integers, I have represented
 /// them as (|a|, sign of a) and (|b|, sign of b). This is because the current project lacks
@@ -163,33 +373,143 @@ pub fn extended_gcd(a: U2048, b: U2048) -> (U2048, BezoutPair) {
     return (old_r, pair);
 }
 
+/// Hash functions that can be used to map data to accumulator elements.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum HashAlgo {
+    Blake2b,
+    Keccak256,
+    Sha256,
+}
+
+/// Computes the 256-bit digest of "data" under "algo".
+fn hash_with(data: &[u8], algo: HashAlgo) -> [u8; 32] {
+    match algo {
+        HashAlgo::Blake2b => return blake2_256(data),
+        HashAlgo::Keccak256 => return keccak_256(data),
+        HashAlgo::Sha256 => {
+            let mut hash: [u8; 32] = [0; 32];
+            hash.copy_from_slice(&Sha256::digest(data));
+            return hash;
+        },
+    }
+}
+
 /// Continuously hashes the input until the result is prime. Assumes input values are transcoded in
 /// little endian(uses parity-scale-codec).
+/// The result is a pure function of the input bytes: the hash functions come from runtime_io and the
+/// primality test(see miller_rabin) uses fixed bases instead of randomness. As a result, the function
+/// builds without the "std" feature(checked by scripts/check-no-std.sh) and the same input bytes yield
+/// the same prime in the wasm runtime and natively. Callers must encode their inputs with fixed-width
+/// integers for this to hold(Ex: binary::hash_index hashes an index as a u64, never as a usize). The
+/// randomized helpers of this module(Ex: miller_rabin_random) are only compiled with "std" and must
+/// never be used to derive accumulator elements.
 /// Consideration: Currently unclear about the impact of Lambda on the security of the scheme.
 pub fn hash_to_prime(elem: &[u8]) -> U2048 {
-    let mut hash = blake2_256(elem);
+    return hash_to_prime_with(elem, HashAlgo::Blake2b);
+}
+
+/// Equivalent to hash_to_prime but uses "algo" for every round of hashing. The same algorithm must
+/// be used when committing and verifying since each one maps an input to a different prime.
+pub fn hash_to_prime_with(elem: &[u8], algo: HashAlgo) -> U2048 {
+    return hash_to_prime_by(elem, algo, miller_rabin);
+}
+
+/// Equivalent to hash_to_prime but tests each candidate with "rounds" rounds of Miller-Rabin(see
+/// miller_rabin_rounds). With at least 5 rounds the test is exact for every candidate, so the result
+/// matches hash_to_prime and additional rounds only cost time.
+pub fn hash_to_prime_rounds(elem: &[u8], rounds: usize) -> U2048 {
+    return hash_to_prime_by(elem, HashAlgo::Blake2b, |n| miller_rabin_rounds(n, rounds));
+}
+
+/// Rehashes the input with "algo" until "is_prime" accepts the candidate.
+fn hash_to_prime_by<F: Fn(U2048) -> bool>(elem: &[u8], algo: HashAlgo, is_prime: F) -> U2048 {
+    let mut hash = hash_with(elem, algo);
     let mut result = U2048::from_little_endian(&hash) % U2048::from(super::LAMBDA);
 
     // While the resulting hash is not a prime, keep trying
-    while !miller_rabin(result) {
-        hash = blake2_256(&hash);
+    while !is_prime(result) {
+        hash = hash_with(&hash, algo);
         result = U2048::from_little_endian(&hash) % U2048::from(super::LAMBDA);
     }
 
     return result;
 }
 
+/// Equivalent to hash_to_prime but separates the input domain by "tag" so that the same data used in
+/// different contexts(Ex: a vector commitment index and a transaction nonce) maps to different
+/// primes. Hashes the length of the tag(as a u32 in little endian) followed by the tag and the data,
+/// so that no two (tag, data) pairs share an input. The empty tag is the untagged domain: it yields the
+/// same prime as hash_to_prime, so existing elements(Ex: the UTXO elements of the runtime) are unchanged.
+/// NOTE: Untagged inputs are not separated from tagged ones, so new contexts should use a non-empty tag.
+pub fn hash_to_prime_tagged(tag: &[u8], elem: &[u8]) -> U2048 {
+    if tag.is_empty() {
+        return hash_to_prime(elem);
+    }
+    let mut data = (tag.len() as u32).to_le_bytes().to_vec();
+    data.extend_from_slice(tag);
+    data.extend_from_slice(elem);
+    return hash_to_prime(&data);
+}
+
+/// Hashes the input together with an incrementing counter until the result is prime and returns the
+/// prime along with the winning counter. Candidate n is blake2_256(elem || n as u64 in little
+/// endian) mod LAMBDA, so the prime can be re-derived from the counter with a single hash and
+/// primality test(see verify_prime_derivation).
+/// NOTE: The primes differ from those of hash_to_prime, which rehashes the previous digest instead.
+pub fn hash_to_prime_with_nonce(elem: &[u8]) -> (U2048, u64) {
+    let mut nonce: u64 = 0;
+    let mut result = get_nonce_candidate(elem, nonce);
+
+    // While the candidate is not a prime, try the next counter
+    while !miller_rabin(result) {
+        nonce += 1;
+        result = get_nonce_candidate(elem, nonce);
+    }
+
+    return (result, nonce);
+}
+
+/// Checks that "prime" is the candidate for "elem" at "nonce" and that it is prime in O(1).
+/// NOTE: This does not check that "nonce" is the first counter that yields a prime, so a prover can
+/// choose among several primes for the same input. Use hash_to_prime when an input must map to a
+/// unique prime.
+pub fn verify_prime_derivation(elem: &[u8], prime: U2048, nonce: u64) -> bool {
+    return get_nonce_candidate(elem, nonce) == prime && miller_rabin(prime);
+}
+
+/// Returns the candidate for "elem" at counter "nonce" used by hash_to_prime_with_nonce.
+fn get_nonce_candidate(elem: &[u8], nonce: u64) -> U2048 {
+    let mut data = elem.to_vec();
+    data.extend_from_slice(&nonce.to_le_bytes());
+    return U2048::from_little_endian(&blake2_256(&data)) % U2048::from(super::LAMBDA);
+}
+
 /// Implements a deterministic variant of the Miller-Rabin primality test for u64/u32 integers based
 /// on the algorithm from the following link: https://en.wikipedia.org/wiki/Miller–Rabin_primality_test
 /// Complexity of the algorithm is O((log n)^4) in soft-O notation.
 pub fn miller_rabin(n: U2048) -> bool {
-    // Find r and d such that 2^r * d + 1 = n
-    let r = (n-U2048::from(1)).trailing_zeros();
-    let d = (n-U2048::from(1)) >> U2048::from(r);
-
     // See https://stackoverflow.com/questions/7594307/simple-deterministic-primality-testing-for-small-numbers
     //let bases = [2,3,5,7,11,13,17]; // Deterministic for 64 bit integers
     let bases = [2, 7, 61];  // Deterministic for 32 bit integers
+    return miller_rabin_bases(n, &bases);
+}
+
+/// Equivalent to miller_rabin but runs "rounds" rounds with the first primes(2, 3, 5, ...) as fixed
+/// bases. The test is exact below 2,152,302,898,747 with at least 5 rounds(every candidate of
+/// hash_to_prime) and below 2^64 with 12 rounds, which is the maximum. With fewer than 5 rounds a
+/// strong pseudoprime(Ex: 3215031751 for the bases 2, 3, 5 and 7) passes. "rounds" is clamped to
+/// [1, 12].
+pub fn miller_rabin_rounds(n: U2048, rounds: usize) -> bool {
+    let bases = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    let rounds = core::cmp::min(core::cmp::max(rounds, 1), bases.len());
+    return miller_rabin_bases(n, &bases[..rounds]);
+}
+
+/// Runs one round of the Miller-Rabin test for each base.
+fn miller_rabin_bases(n: U2048, bases: &[u32]) -> bool {
+    // Find r and d such that 2^r * d + 1 = n
+    let r = (n-U2048::from(1)).trailing_zeros();
+    let d = (n-U2048::from(1)) >> U2048::from(r);
 
     'outer: for &a in bases.iter() {
         // Annoying edge case to make sure a is within [2, n-2] for small n
@@ -211,6 +531,95 @@ pub fn miller_rabin(n: U2048) -> bool {
     return true;
 }
 
+/// Generates an RSA modulus with "bits" bits as the product of two safe primes. If "keep_factors"
+/// is true, the factors are also returned. Anyone holding the factors can forge witnesses, so they
+/// should only be kept for tests or trapdoor scenarios.
+/// NOTE: "bits" must be within [11, 2046] since mul_mod overflows for moduli above 2^2047. Below 11
+/// bits there are no two distinct safe primes of bits/2 and bits - bits/2 bits whose product has
+/// exactly "bits" bits(Ex: 11 is the only safe prime with 4 bits).
+#[cfg(feature = "std")]
+pub fn generate_modulus<R: RngCore>(bits: usize, rng: &mut R, keep_factors: bool) -> (U2048, Option<(U2048, U2048)>) {
+    assert!(bits >= 11 && bits <= 2046, "generate_modulus: bits must be in [11, 2046]");
+    loop {
+        let p = generate_safe_prime(bits / 2, rng);
+        let q = generate_safe_prime(bits - bits / 2, rng);
+        let modulus = p * q;
+        if p == q || modulus.bits() != bits {
+            continue;
+        }
+        if keep_factors {
+            return (modulus, Some((p, q)));
+        }
+        return (modulus, None);
+    }
+}
+
+/// Generates a prime p with "bits" bits such that (p-1)/2 is also prime.
+#[cfg(feature = "std")]
+fn generate_safe_prime<R: RngCore>(bits: usize, rng: &mut R) -> U2048 {
+    let small_primes = [3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47];
+    loop {
+        // The top bit of q is set so that p = 2q + 1 has exactly "bits" bits
+        let q = random_bits(bits - 1, rng) | (U2048::from(1) << (bits - 2)) | U2048::from(1);
+        let p = q * U2048::from(2) + U2048::from(1);
+
+        // Cheaply reject candidates with small factors before running Miller-Rabin
+        if small_primes.iter().any(|&s| (q % U2048::from(s) == U2048::from(0) && q != U2048::from(s))
+            || (p % U2048::from(s) == U2048::from(0) && p != U2048::from(s))) {
+            continue;
+        }
+        if miller_rabin_random(q, 40, rng) && miller_rabin_random(p, 40, rng) {
+            return p;
+        }
+    }
+}
+
+/// Returns a uniformly random integer below 2^bits.
+#[cfg(feature = "std")]
+fn random_bits<R: RngCore>(bits: usize, rng: &mut R) -> U2048 {
+    let mut bytes: [u8; 256] = [0; 256];
+    rng.fill_bytes(&mut bytes);
+    let result = U2048::from_little_endian(&bytes);
+    if bits >= 2048 {
+        return result;
+    }
+    return result & ((U2048::from(1) << bits) - U2048::from(1));
+}
+
+/// Probabilistic variant of miller_rabin for integers of arbitrary size. Each round uses a random
+/// base, so a composite passes with probability at most 4^(-rounds).
+#[cfg(feature = "std")]
+pub fn miller_rabin_random<R: RngCore>(n: U2048, rounds: usize, rng: &mut R) -> bool {
+    if n < U2048::from(4) {
+        return n == U2048::from(2) || n == U2048::from(3);
+    }
+    if n % U2048::from(2) == U2048::from(0) {
+        return false;
+    }
+
+    // Find r and d such that 2^r * d + 1 = n
+    let r = (n-U2048::from(1)).trailing_zeros();
+    let d = (n-U2048::from(1)) >> U2048::from(r);
+
+    'outer: for _ in 0..rounds {
+        // Random base within [2, n-2]
+        let a = random_bits(n.bits(), rng) % (n - U2048::from(3)) + U2048::from(2);
+        let mut x = mod_exp(a, d, n);
+
+        if x == U2048::from(1) || x == (n-U2048::from(1)) {
+            continue;
+        }
+        for _ in 1..r {
+            x = mul_mod(x, x, n);
+            if x == (n-U2048::from(1)) {
+                continue 'outer;
+            }
+        }
+        return false;
+    }
+    return true;
+}
+
 /// Given an element g and a set of elements x, computes the xith root of g^x for each element
 /// in the set. Runs in O(n log(n)).
 pub fn root_factor(g: U2048, elems: &[U2048]) -> Vec<U2048> {
@@ -266,6 +675,164 @@ mod tests {
         assert_eq!(mod_exp(U2048::from(7), U2048::from(15), U2048::from_dec_str(MODULUS).unwrap()), U2048::from(5));
     }
 
+    #[test]
+    fn test_mod_exp_windowed() {
+        assert_eq!(mod_exp_windowed(U2048::from(2), U2048::from(7), U2048::from_dec_str(MODULUS).unwrap()), U2048::from(11));
+        assert_eq!(mod_exp_windowed(U2048::from(7), U2048::from(0), U2048::from_dec_str(MODULUS).unwrap()), U2048::from(1));
+
+        // Compare against mod_exp for pseudorandom inputs
+        let mut seed = blake2_256(b"mod_exp_windowed");
+        for _ in 0..100 {
+            let base = U2048::from_little_endian(&seed[0..16]);
+            let exp = U2048::from_little_endian(&seed[16..32]);
+            let modulus = U2048::from_little_endian(&seed[0..8]) + U2048::from(1);
+            assert_eq!(mod_exp_windowed(base, exp, modulus), mod_exp(base, exp, modulus));
+            assert_eq!(mod_exp_windowed(base, exp, U2048::from_dec_str(MODULUS).unwrap()),
+                       mod_exp(base, exp, U2048::from_dec_str(MODULUS).unwrap()));
+            for window_size in 1..7 {
+                assert_eq!(mod_exp_window_size(base, exp, modulus, window_size), mod_exp(base, exp, modulus));
+            }
+            seed = blake2_256(&seed);
+        }
+    }
+
+    #[test]
+    fn test_multi_exp() {
+        assert_eq!(multi_exp(&[]), U2048::from(1));
+        assert_eq!(multi_exp(&[(U2048::from(2), U2048::from(7))]), U2048::from(11));
+        assert_eq!(multi_exp(&[(U2048::from(2), U2048::from(7)), (U2048::from(7), U2048::from(0))]), U2048::from(11));
+
+        // Compare against a loop over mod_exp for 50 pseudorandom pairs
+        let mut seed = blake2_256(b"multi_exp");
+        let modulus = U2048::from_little_endian(&seed[0..8]) + U2048::from(1);
+        let mut pairs: Vec<(U2048, U2048)> = Vec::new();
+        for _ in 0..50 {
+            seed = blake2_256(&seed);
+            pairs.push((U2048::from_little_endian(&seed[0..16]), U2048::from_little_endian(&seed[16..32])));
+        }
+        for &modulus in [U2048::from_dec_str(MODULUS).unwrap(), modulus].iter() {
+            let expected = pairs.iter().fold(U2048::from(1), |acc, (base, exp)| mul_mod(acc, mod_exp(*base, *exp, modulus), modulus));
+            assert_eq!(multi_exp_with_modulus(&pairs, modulus), expected);
+        }
+    }
+
+    #[test]
+    fn test_precomputed_base() {
+        let modulus = U2048::from_dec_str(MODULUS).unwrap();
+        let generator = PrecomputedBase::new(U2048::from(2), modulus, 4);
+        assert_eq!(generator.exp(U2048::from(7)), U2048::from(11));
+        assert_eq!(generator.exp(U2048::from(0)), U2048::from(1));
+        assert_eq!(generator.exp(U2048::max_value()), mod_exp(U2048::from(2), U2048::max_value(), modulus));
+
+        // Compare against mod_exp for 1000 pseudorandom exponents of the generator
+        let mut seed = blake2_256(b"precomputed_base");
+        for _ in 0..1000 {
+            seed = blake2_256(&seed);
+            let exp = U2048::from_little_endian(&seed);
+            assert_eq!(generator.exp(exp), mod_exp(U2048::from(2), exp, modulus));
+        }
+
+        // Pseudorandom bases under a large modulus for several window sizes
+        for window in [1, 3, 5, 7].iter() {
+            seed = blake2_256(&seed);
+            let base = U2048::from_little_endian(&seed[0..16]);
+            let modulus = U2048::from_little_endian(&seed[16..24]) + U2048::from(1);
+            let table = PrecomputedBase::new(base, modulus, *window);
+            for _ in 0..10 {
+                seed = blake2_256(&seed);
+                let exp = U2048::from_little_endian(&seed);
+                assert_eq!(table.exp(exp), mod_exp(base, exp, modulus));
+            }
+        }
+    }
+
+    #[test]
+    fn test_mod_exp_ct() {
+        assert_eq!(mod_exp_ct(U2048::from(2), U2048::from(7), U2048::from_dec_str(MODULUS).unwrap()), U2048::from(11));
+        assert_eq!(mod_exp_ct(U2048::from(7), U2048::from(0), U2048::from_dec_str(MODULUS).unwrap()), U2048::from(1));
+
+        // Compare against mod_exp for pseudorandom inputs
+        let mut seed = blake2_256(b"mod_exp_ct");
+        for _ in 0..100 {
+            let base = U2048::from_little_endian(&seed[0..16]);
+            let exp = U2048::from_little_endian(&seed[16..32]);
+            assert_eq!(mod_exp_ct(base, exp, U2048::from_dec_str(MODULUS).unwrap()),
+                       mod_exp(base, exp, U2048::from_dec_str(MODULUS).unwrap()));
+            seed = blake2_256(&seed);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_miller_rabin_random() {
+        use rand::{SeedableRng, rngs::StdRng};
+        let mut rng = StdRng::seed_from_u64(0);
+        for n in 0..1000u32 {
+            assert_eq!(miller_rabin_random(U2048::from(n), 20, &mut rng), n >= 2 && miller_rabin(U2048::from(n)));
+        }
+        // Carmichael number and a 2^61 - 1 Mersenne prime
+        assert_eq!(miller_rabin_random(U2048::from(561), 20, &mut rng), false);
+        assert_eq!(miller_rabin_random(U2048::from(2305843009213693951u64), 20, &mut rng), true);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_generate_modulus() {
+        use rand::{SeedableRng, rngs::StdRng};
+        let mut rng = StdRng::seed_from_u64(0);
+        let (modulus, factors) = generate_modulus(64, &mut rng, true);
+        let (p, q) = factors.unwrap();
+        assert_eq!(p * q, modulus);
+        assert_eq!(modulus.bits(), 64);
+        for &factor in [p, q].iter() {
+            assert_eq!(miller_rabin(factor), true);
+            assert_eq!(miller_rabin((factor - U2048::from(1)) / U2048::from(2)), true);
+        }
+
+        let (modulus, factors) = generate_modulus(64, &mut rng, false);
+        assert_eq!(factors, None);
+        assert_eq!(modulus.bits(), 64);
+
+        // The smallest sizes only have a handful of safe primes(Ex: 23 with 5 bits, 47 and 59 with 6)
+        let (modulus, factors) = generate_modulus(11, &mut rng, true);
+        assert_eq!(modulus.bits(), 11);
+        assert_eq!(factors.unwrap().0, U2048::from(23));
+        let (modulus, _) = generate_modulus(12, &mut rng, false);
+        assert_eq!(modulus, U2048::from(47 * 59));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    #[should_panic(expected = "generate_modulus: bits must be in [11, 2046]")]
+    fn test_generate_modulus_too_small() {
+        use rand::{SeedableRng, rngs::StdRng};
+        let mut rng = StdRng::seed_from_u64(0);
+        generate_modulus(8, &mut rng, false);
+    }
+
+    #[test]
+    fn test_mod_mul_ct() {
+        let modulus = U2048::from_dec_str(MODULUS).unwrap();
+        assert_eq!(mod_mul_ct(U2048::from(5), U2048::from(6), modulus), U2048::from(4));
+        assert_eq!(mod_mul_ct(U2048::from(12), U2048::from(0), modulus), U2048::from(0));
+
+        // Compare against mul_mod for pseudorandom inputs under MODULUS and a 2047-bit modulus
+        let large_modulus = (U2048::from(1) << 2047) - U2048::from(1);
+        let mut seed = blake2_256(b"mod_mul_ct");
+        for _ in 0..20 {
+            let a = U2048::from_little_endian(&seed[0..16]);
+            let b = U2048::from_little_endian(&seed[16..32]);
+            assert_eq!(mod_mul_ct(a % modulus, b % modulus, modulus), mul_mod(a, b, modulus));
+            assert_eq!(mod_mul_ct(a, b, large_modulus), mul_mod(a, b, large_modulus));
+            seed = blake2_256(&seed);
+        }
+
+        // The conditional subtraction handles sums that overflow 2048 bits
+        let top_modulus = U2048::max_value() - U2048::from(10);
+        assert_eq!(mod_add_ct(top_modulus - U2048::from(1), top_modulus - U2048::from(2), top_modulus), top_modulus - U2048::from(3));
+        assert_eq!(mod_mul_ct(top_modulus - U2048::from(1), U2048::from(2), top_modulus), top_modulus - U2048::from(2));
+    }
+
     #[test]
     fn test_extended_gcd() {
         assert_eq!(extended_gcd(U2048::from(180), U2048::from(150)), (U2048::from(30),
@@ -274,6 +841,45 @@ mod tests {
                    BezoutPair {coefficient_a: U2048::from(4), coefficient_b: U2048::from(3), sign_a: false, sign_b: true}));
     }
 
+    #[test]
+    fn test_xgcd() {
+        assert_eq!(xgcd(U2048::from(180), U2048::from(150)), (U2048::from(30), (U2048::from(1), false), (U2048::from(1), true)));
+        assert_eq!(xgcd(U2048::from(240), U2048::from(46)), (U2048::from(2), (U2048::from(9), true), (U2048::from(47), false)));
+        assert_eq!(xgcd(U2048::from(0), U2048::from(5)), (U2048::from(5), (U2048::from(0), false), (U2048::from(1), false)));
+        assert_eq!(xgcd(U2048::from(5), U2048::from(0)), (U2048::from(5), (U2048::from(1), false), (U2048::from(0), false)));
+
+        // Check a*x + b*y = gcd for pseudorandom 32 bit inputs
+        let signed = |(value, negative): (U2048, bool)| -> i128 {
+            if negative { -(value.low_u64() as i128) } else { value.low_u64() as i128 }
+        };
+        let mut seed = blake2_256(b"xgcd");
+        for _ in 0..100 {
+            let a = U2048::from_little_endian(&seed[0..4]);
+            let b = U2048::from_little_endian(&seed[4..8]) * U2048::from(6);
+            let (gcd, x, y) = xgcd(a, b);
+            assert_eq!(a.low_u64() as i128 * signed(x) + b.low_u64() as i128 * signed(y), gcd.low_u64() as i128);
+            assert_eq!(a % gcd, U2048::from(0));
+            assert_eq!(b % gcd, U2048::from(0));
+            seed = blake2_256(&seed);
+        }
+    }
+
+    #[test]
+    fn test_checked_mod_inverse() {
+        let modulus = U2048::from(3233);
+        for &a in [2u64, 3, 7, 42, 3232, 5000].iter() {
+            let inverse = checked_mod_inverse(U2048::from(a), modulus).unwrap();
+            assert_eq!(inverse < modulus, true);
+            assert_eq!(mul_mod(U2048::from(a), inverse, modulus), U2048::from(1));
+        }
+        assert_eq!(checked_mod_inverse(U2048::from(5), U2048::from_dec_str(MODULUS).unwrap()), Some(U2048::from(8)));
+
+        // 3233 = 61 * 53
+        assert_eq!(checked_mod_inverse(U2048::from(61), modulus), None);
+        assert_eq!(checked_mod_inverse(U2048::from(106), modulus), None);
+        assert_eq!(checked_mod_inverse(U2048::from(0), modulus), None);
+    }
+
     #[test]
     fn test_bezout() {
         assert_eq!(bezout(U2048::from(4), U2048::from(10)), None);
@@ -289,6 +895,26 @@ mod tests {
         assert_eq!(shamir_trick(U2048::from(12), U2048::from(7), U2048::from(7), U2048::from(11)), None);
     }
 
+    #[test]
+    fn test_shamir_trick_with_modulus() {
+        // g = r^(xy) so that w_1 = r^y and w_2 = r^x are the xth and yth roots of g
+        let modulus = U2048::from(3233);
+        let r = U2048::from(42);
+        let (x, y) = (U2048::from(7), U2048::from(11));
+        let (w_1, w_2) = (mod_exp(r, y, modulus), mod_exp(r, x, modulus));
+        assert_eq!(shamir_trick_with_modulus(w_1, w_2, x, y, modulus), Some(r));
+        assert_eq!(mod_exp(r, x * y, modulus), mod_exp(w_1, x, modulus));
+
+        // Valid roots of the same element, but x and y share a factor of 3
+        let (x, y) = (U2048::from(6), U2048::from(9));
+        let (w_1, w_2) = (mod_exp(r, y, modulus), mod_exp(r, x, modulus));
+        assert_eq!(mod_exp(w_1, x, modulus), mod_exp(w_2, y, modulus));
+        assert_eq!(shamir_trick_with_modulus(w_1, w_2, x, y, modulus), None);
+
+        // Roots of different elements
+        assert_eq!(shamir_trick_with_modulus(U2048::from(5), U2048::from(6), U2048::from(7), U2048::from(11), modulus), None);
+    }
+
     #[test]
     fn test_mod_inverse() {
         assert_eq!(mod_inverse(U2048::from(9)), U2048::from(3));
@@ -317,8 +943,81 @@ mod tests {
 
     #[test]
     fn test_hash_to_prime() {
-        //assert_eq!(hash_to_prime(&[7, 10]), U2048::from(...));
-        // Key values checked: 0, 1, 2
+        // Fixed expected primes so that any platform-dependent derivation is caught
+        assert_eq!(hash_to_prime(&[7, 10]), U2048::from(281384533));
+        assert_eq!(hash_to_prime(&[0]), U2048::from(1166309897));
+        assert_eq!(hash_to_prime(&[]), U2048::from(3151033447u32));
+        for i in 0..3u8 {
+            assert_eq!(hash_to_prime(&[i]), hash_to_prime(&[i]));
+        }
+    }
+
+    #[test]
+    fn test_miller_rabin_rounds() {
+        // Strong pseudoprime to the bases 2, 3, 5 and 7
+        let pseudoprime = U2048::from(3215031751u64);
+        assert_eq!(miller_rabin(pseudoprime), false);
+        assert_eq!(miller_rabin_rounds(pseudoprime, 4), true);
+        for rounds in 5..14 {
+            assert_eq!(miller_rabin_rounds(pseudoprime, rounds), false);
+            assert_eq!(miller_rabin_rounds(U2048::from(561), rounds), false);
+            assert_eq!(miller_rabin_rounds(U2048::from(4222234741u64), rounds), true);
+        }
+        assert_eq!(miller_rabin_rounds(U2048::from(2047), 0), true);
+        assert_eq!(miller_rabin_rounds(U2048::from(2047), 2), false);
+    }
+
+    #[test]
+    fn test_hash_to_prime_rounds() {
+        for i in 0..10u8 {
+            let prime = hash_to_prime(&[i]);
+            for rounds in 5..13 {
+                assert_eq!(hash_to_prime_rounds(&[i], rounds), prime);
+            }
+        }
+        assert_eq!(hash_to_prime_rounds(&[7, 10], 12), U2048::from(281384533));
+    }
+
+    #[test]
+    fn test_hash_to_prime_tagged() {
+        let index = 3usize.to_le_bytes();
+        let prime = hash_to_prime_tagged(b"vc", &index);
+        assert_eq!(miller_rabin(prime), true);
+        assert_eq!(hash_to_prime_tagged(b"vc", &index), prime);
+        assert_ne!(hash_to_prime_tagged(b"nonce", &index), prime);
+        assert_ne!(hash_to_prime(&index), prime);
+
+        // The tag boundary is part of the input
+        assert_ne!(hash_to_prime_tagged(b"ab", b"c"), hash_to_prime_tagged(b"a", b"bc"));
+
+        // The empty tag keeps the output of hash_to_prime
+        assert_eq!(hash_to_prime_tagged(b"", &[7, 10]), U2048::from(281384533));
+        assert_eq!(hash_to_prime_tagged(b"", &index), hash_to_prime(&index));
+    }
+
+    #[test]
+    fn test_hash_to_prime_with_nonce() {
+        for i in 0..10u32 {
+            let (prime, nonce) = hash_to_prime_with_nonce(&i.to_le_bytes());
+            assert_eq!(miller_rabin(prime), true);
+            assert_eq!(hash_to_prime_with_nonce(&i.to_le_bytes()), (prime, nonce));
+            assert_eq!(verify_prime_derivation(&i.to_le_bytes(), prime, nonce), true);
+            assert_eq!(verify_prime_derivation(&i.to_le_bytes(), prime, nonce + 1), false);
+            assert_eq!(verify_prime_derivation(&(i + 10).to_le_bytes(), prime, nonce), false);
+        }
+    }
+
+    #[test]
+    fn test_hash_to_prime_with() {
+        let algos = [HashAlgo::Blake2b, HashAlgo::Keccak256, HashAlgo::Sha256];
+        for algo in algos.iter() {
+            let prime = hash_to_prime_with(&[7, 10], *algo);
+            assert_eq!(hash_to_prime_with(&[7, 10], *algo), prime);
+            assert_eq!(miller_rabin(prime), true);
+        }
+        assert_eq!(hash_to_prime_with(&[7, 10], HashAlgo::Blake2b), hash_to_prime(&[7, 10]));
+        assert_ne!(hash_to_prime_with(&[7, 10], HashAlgo::Keccak256), hash_to_prime_with(&[7, 10], HashAlgo::Sha256));
+        assert_ne!(hash_to_prime_with(&[7, 10], HashAlgo::Blake2b), hash_to_prime_with(&[7, 10], HashAlgo::Keccak256));
     }
 
     #[test]
@@ -0,0 +1,217 @@
+/// 4096-bit Accumulator Functions.
+/// The 4096-bit path duplicates the subset of the U2048 functions that it needs instead of making
+/// them generic over the integer width. The uint types do not share a common trait, and a generic
+/// U2048 would change every public signature as well as the SCALE encoding of Witness that the
+/// runtime stores on-chain. Accumulator elements are at most LAMBDA bits so hash_to_prime is shared.
+
+use codec::{Encode, Decode};
+use crate::subroutines;
+use super::U2048;
+
+construct_uint! {
+    #[derive(Encode, Decode)]
+	pub struct U4096(64);
+}
+
+/// Defines the 4096-bit RSA group. Arbitrary set at MODULUS = 13 for testing.
+pub const MODULUS: &str = "13";
+
+/// A witness can either be a membership witness or a non-membership witness.
+#[derive(Encode, Decode, Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Witness {
+    MemWit(U4096),
+    NonMemWit((U4096, bool, U4096)),
+}
+
+/// Converts a U2048 into a U4096.
+pub fn widen(elem: U2048) -> U4096 {
+    let mut bytes: [u8; 256] = [0; 256];
+    elem.to_little_endian(&mut bytes);
+    return U4096::from_little_endian(&bytes);
+}
+
+/// Equivalent to subroutines::hash_to_prime.
+pub fn hash_to_prime(elem: &[u8]) -> U4096 {
+    return widen(subroutines::hash_to_prime(elem));
+}
+
+/// Equivalent to subroutines::mod_exp.
+pub fn mod_exp(mut base: U4096, mut exp: U4096, modulus: U4096) -> U4096 {
+    let mut result: U4096 = U4096::from(1);
+    base = base % modulus;
+    while exp > U4096::from(0) {
+        if exp % U4096::from(2) == U4096::from(1) {
+            result = mul_mod(result, base, modulus);
+        }
+
+        if exp == U4096::from(1) {
+            return result;
+        }
+
+        exp = exp >> U4096::from(1);
+        base = mul_mod(base, base, modulus);
+    }
+    return result;
+}
+
+/// Equivalent to subroutines::mul_mod.
+pub fn mul_mod(mut a: U4096, mut b: U4096, modulus: U4096) -> U4096 {
+    let mut result = U4096::from(0);
+    a = a % modulus;
+    while b > U4096::from(0) {
+        if b % U4096::from(2) == U4096::from(1) {
+            result = (result + a) % modulus;
+        }
+
+        a = (a * U4096::from(2)) % modulus;
+        b /= U4096::from(2);
+    }
+    return result % modulus;
+}
+
+/// Equivalent to subroutines::extended_gcd. The Bezout coefficients are returned as
+/// (|a|, sign of a, |b|, sign of b) where true indicates negative.
+pub fn extended_gcd(a: U4096, b: U4096) -> (U4096, (U4096, bool, U4096, bool)) {
+    let (mut s, mut old_s): (U4096, U4096) = (U4096::from(0), U4096::from(1));
+    let (mut t, mut old_t): (U4096, U4096) = (U4096::from(1), U4096::from(0));
+    let (mut r, mut old_r): (U4096, U4096) = (b, a);
+
+    let (mut prev_sign_s, mut prev_sign_t): (bool, bool) = (false, false);
+    let (mut sign_s, mut sign_t): (bool, bool) = (false, false);
+
+    while r != U4096::from(0) {
+        let quotient = old_r/r;
+        let new_r = old_r - quotient * r;
+        old_r = r;
+        r = new_r;
+
+        // Track the coefficient "a" as (|a|, sign of a)
+        let mut new_s = quotient * s;
+        if prev_sign_s == sign_s && new_s > old_s {
+            new_s = new_s - old_s;
+            sign_s = !sign_s;
+        }
+        else if prev_sign_s != sign_s {
+            new_s = old_s + new_s;
+            prev_sign_s = sign_s;
+            sign_s = !sign_s;
+        }
+        else { new_s = old_s - new_s; }
+        old_s = s;
+        s = new_s;
+
+        // Track the coefficient "b" as (|b|, sign of b)
+        let mut new_t = quotient * t;
+        if prev_sign_t == sign_t && new_t > old_t {
+            new_t = new_t - old_t;
+            sign_t = !sign_t;
+        }
+        else if prev_sign_t != sign_t {
+            new_t = old_t + new_t;
+            prev_sign_t = sign_t;
+            sign_t = !sign_t;
+        }
+        else { new_t = old_t - new_t; }
+        old_t = t;
+        t = new_t;
+    }
+
+    return (old_r, (old_s, prev_sign_s, old_t, prev_sign_t));
+}
+
+/// Equivalent to subroutines::bezout.
+pub fn bezout(a: U4096, b: U4096) -> Option<(U4096, bool, U4096, bool)> {
+    let (gcd, pair) = extended_gcd(a, b);
+    if gcd != U4096::from(1) {
+        return None;
+    }
+    return Some(pair);
+}
+
+/// Equivalent to subroutines::mod_inverse.
+pub fn mod_inverse(elem: U4096) -> U4096 {
+    let modulus = U4096::from_dec_str(MODULUS).unwrap();
+    let (_, (coefficient_a, sign_a, _, _)) = extended_gcd(elem, modulus);
+    if sign_a {
+        return (modulus - coefficient_a) % modulus;
+    }
+    return coefficient_a % modulus;
+}
+
+/// Equivalent to witnesses::mem_wit_create.
+pub fn mem_wit_create(old_state: U4096, agg: U4096, elem: U4096) -> Option<U4096> {
+    if agg % elem != U4096::from(0) {
+        return None;
+    }
+    return Some(mod_exp(old_state, agg / elem, U4096::from_dec_str(MODULUS).unwrap()));
+}
+
+/// Equivalent to witnesses::verify_mem_wit.
+pub fn verify_mem_wit(state: U4096, witness: U4096, elem: U4096) -> bool {
+    return mod_exp(witness, elem, U4096::from_dec_str(MODULUS).unwrap()) == state;
+}
+
+/// Equivalent to witnesses::non_mem_wit_create. Returns None if "elem" is contained in "agg_elems".
+pub fn non_mem_wit_create(mut old_state: U4096, agg_elems: U4096, elem: U4096) -> Option<(U4096, bool, U4096)> {
+    let (coefficient_a, sign_a, coefficient_b, sign_b) = bezout(agg_elems, elem)?;
+    if sign_b {
+        old_state = mod_inverse(old_state);
+    }
+    let B = mod_exp(old_state, coefficient_b, U4096::from_dec_str(MODULUS).unwrap());
+    return Some((coefficient_a, sign_a, B));
+}
+
+/// Equivalent to witnesses::verify_non_mem_wit.
+pub fn verify_non_mem_wit(old_state: U4096, mut state: U4096, witness: (U4096, bool, U4096), elem: U4096) -> bool {
+    let (a, sign_a, B) = witness;
+    if sign_a {
+        state = mod_inverse(state);
+    }
+
+    let exp_1 = mod_exp(state, a, U4096::from_dec_str(MODULUS).unwrap());
+    let exp_2 = mod_exp(B, elem, U4096::from_dec_str(MODULUS).unwrap());
+    return mul_mod(exp_1, exp_2, U4096::from_dec_str(MODULUS).unwrap()) == old_state;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_widen() {
+        assert_eq!(widen(U2048::max_value()) + U4096::from(1), U4096::from(1) << 2048);
+        assert_eq!(hash_to_prime(&[7]), widen(subroutines::hash_to_prime(&[7])));
+    }
+
+    #[test]
+    fn test_mod_exp() {
+        assert_eq!(mod_exp(U4096::from(2), U4096::from(7), U4096::from_dec_str(MODULUS).unwrap()), U4096::from(11));
+        assert_eq!(mod_exp(U4096::from(7), U4096::from(15), U4096::from_dec_str(MODULUS).unwrap()), U4096::from(5));
+    }
+
+    #[test]
+    fn test_extended_gcd() {
+        assert_eq!(extended_gcd(U4096::from(180), U4096::from(150)), (U4096::from(30), (U4096::from(1), false, U4096::from(1), true)));
+        assert_eq!(extended_gcd(U4096::from(13), U4096::from(17)), (U4096::from(1), (U4096::from(4), false, U4096::from(3), true)));
+    }
+
+    #[test]
+    fn test_witnesses() {
+        // The product of the elements exceeds 2048 bits
+        let elems: Vec<U4096> = (0..80u32).map(|i| hash_to_prime(&i.to_le_bytes())).collect();
+        let mut product = U4096::from(1);
+        for elem in elems[1..].iter() {
+            product *= *elem;
+        }
+        assert_eq!(product.bits() > 2048, true);
+        let state = mod_exp(U4096::from(2), product, U4096::from_dec_str(MODULUS).unwrap());
+
+        let mem_wit = mem_wit_create(U4096::from(2), product, elems[1]).unwrap();
+        assert_eq!(verify_mem_wit(state, mem_wit, elems[1]), true);
+        assert_eq!(mem_wit_create(U4096::from(2), product, elems[0]), None);
+
+        let non_mem_wit = non_mem_wit_create(U4096::from(2), product, elems[0]).unwrap();
+        assert_eq!(verify_non_mem_wit(U4096::from(2), state, non_mem_wit, elems[0]), true);
+        assert_eq!(non_mem_wit_create(U4096::from(2), product, elems[1]), None);
+    }
+}
@@ -3,26 +3,63 @@
 use crate::subroutines;
 use crate::proofs;
 use rstd::prelude::Vec;
-use super::U2048;
+use super::{U2048, Witness};
 
 /// Given an old state, the product of a set of elements that have been added, and a single element from that
 /// set, returns the witness for that element.
 /// NOTE: "old_state" represents the state *before* the elements are added.
 /// This function will likely be used by an online user.
 pub fn mem_wit_create(old_state: U2048, agg: U2048, elem: U2048) -> Option<U2048> {
+    return mem_wit_create_with_modulus(old_state, agg, elem, U2048::from_dec_str(super::MODULUS).unwrap());
+}
+
+/// Equivalent to mem_wit_create for an arbitrary modulus.
+pub fn mem_wit_create_with_modulus(old_state: U2048, agg: U2048, elem: U2048, modulus: U2048) -> Option<U2048> {
     if agg % elem != U2048::from(0) {
         return None;
     }
     let quotient = agg / elem;
-    return Some(subroutines::mod_exp(old_state, quotient, U2048::from_dec_str(super::MODULUS).unwrap()));
+    return Some(subroutines::mod_exp(old_state, quotient, modulus));
+}
+
+/// Equivalent to mem_wit_create but raises "old_state" to the quotient with mod_exp_ct, since the
+/// quotient encodes the other committed values. The ladder always runs over 2048 bits, so it is
+/// several times slower for short quotients.
+/// NOTE: Only the exponentiation is constant-time. The division of "agg" by "elem" is not.
+pub fn mem_wit_create_ct(old_state: U2048, agg: U2048, elem: U2048) -> Option<U2048> {
+    let modulus = U2048::from_dec_str(super::MODULUS).unwrap();
+    if agg % elem != U2048::from(0) {
+        return None;
+    }
+    return Some(subroutines::mod_exp_ct(old_state, agg / elem, modulus));
 }
 
 /// Verify the witness of an element.
 pub fn verify_mem_wit(state: U2048, witness: U2048, elem: U2048) -> bool {
-    let result = subroutines::mod_exp(witness, elem, U2048::from_dec_str(super::MODULUS).unwrap());
+    return verify_mem_wit_with_modulus(state, witness, elem, U2048::from_dec_str(super::MODULUS).unwrap());
+}
+
+/// Equivalent to verify_mem_wit for an arbitrary modulus.
+pub fn verify_mem_wit_with_modulus(state: U2048, witness: U2048, elem: U2048, modulus: U2048) -> bool {
+    let result = subroutines::mod_exp(witness, elem, modulus);
     return result == state;
 }
 
+/// Refreshes a membership witness after the elements with product "additions" are added to the
+/// accumulator: w' = w^additions. Equivalent to update_mem_wit without deletions but never fails.
+pub fn update_mem_wit_on_add(witness: U2048, additions: U2048, modulus: U2048) -> U2048 {
+    return subroutines::mod_exp(witness, additions, modulus);
+}
+
+/// Refreshes the membership witness of "elem" after the elements with product "deletions" are
+/// deleted and the accumulator moves to "new_state". Since roots cannot be computed directly, the
+/// old witness(an elem-th root of the old state) and "new_state"(a deletions-th root of the old
+/// state) are combined with the Shamir trick into an elem-th root of "new_state". Returns None if
+/// "elem" itself was deleted(elem and deletions are not coprime) or the inputs are inconsistent.
+pub fn update_mem_wit_on_delete(elem: U2048, witness: U2048, new_state: U2048, deletions: U2048, modulus: U2048) -> Option<U2048> {
+    return subroutines::shamir_trick_with_modulus(witness, new_state, elem, deletions, modulus);
+}
+
 /// Updates a membership witness based on untracked additions and deletions. Algorithm is based on
 /// section 3.2 of the paper titled "Dynamic Accumulators and Applications to Efficient Revocation of
 /// Anonymous Credentials". Note that "additions" represent the product of the added elements
@@ -30,10 +67,10 @@ pub fn verify_mem_wit(state: U2048, witness: U2048, elem: U2048) -> bool {
 /// NOTE: Does not do any error checking on unwrap.
 pub fn update_mem_wit(elem: U2048, mut witness: U2048, new_state: U2048, additions: U2048, deletions: U2048) -> U2048 {
     // Handle added elems
-    witness = subroutines::mod_exp(witness, additions, U2048::from_dec_str(super::MODULUS).unwrap());
+    witness = update_mem_wit_on_add(witness, additions, U2048::from_dec_str(super::MODULUS).unwrap());
 
     // Handle deleted elems
-    witness = subroutines::shamir_trick(witness, new_state, elem, deletions).unwrap();
+    witness = update_mem_wit_on_delete(elem, witness, new_state, deletions, U2048::from_dec_str(super::MODULUS).unwrap()).unwrap();
     return witness;
 }
 
@@ -46,6 +83,36 @@ pub fn agg_mem_wit(state: U2048, witness_x: U2048, witness_y: U2048, x: U2048, y
     return (aggregated, proof);
 }
 
+/// Folds a set of membership witnesses(each paired with its element) into a single membership
+/// witness for the product of the elements using the Shamir trick. Returns None if the set is empty,
+/// contains a non-membership witness, or if the elements are not pairwise coprime.
+pub fn aggregate_witnesses(w: &[(Witness, U2048)]) -> Option<Witness> {
+    let (mut aggregated, mut agg_elems) = match w.first()? {
+        (Witness::MemWit(witness), elem) => (*witness, *elem),
+        (Witness::NonMemWit(_), _) => return None,
+    };
+
+    for (witness, elem) in w[1..].iter() {
+        match witness {
+            Witness::MemWit(witness) => {
+                aggregated = subroutines::shamir_trick(aggregated, *witness, agg_elems, *elem)?;
+                agg_elems *= *elem;
+            },
+            Witness::NonMemWit(_) => return None,
+        }
+    }
+    return Some(Witness::MemWit(aggregated));
+}
+
+/// Inverse of aggregate_witnesses. Given a membership witness for the product of a set of elements
+/// and the product "others" of every element in the set except the target, returns the membership
+/// witness for the target: agg_witness^others.
+/// NOTE: The function does not detect a target that was not part of the aggregate(or a wrong
+/// "others"). The result then simply fails to verify, so callers must verify it with verify_mem_wit.
+pub fn disaggregate_mem_wit(agg_witness: U2048, others: U2048, modulus: U2048) -> U2048 {
+    return subroutines::mod_exp(agg_witness, others, modulus);
+}
+
 /// Verifies that a membership witness + proof for a set of accumulator elements are valid. Acts as a
 /// wrapper for the proof of exponentiation verifier.
 pub fn verify_agg_mem_wit(state: U2048, agg_elems: U2048, witness: U2048, proof: U2048) -> bool {
@@ -68,32 +135,116 @@ pub fn create_all_mem_wit(old_state: U2048, new_elems: &[U2048]) -> Vec<U2048> {
 /// raised to the "agg_elems" power(represents product of added elements). The second value of the
 /// tuple is the sign of the first value since the Bezout coefficient may be negative.
 /// NOTE: Function assumes that "elem" is not contained in "agg_elems"
-pub fn non_mem_wit_create(mut old_state: U2048, agg_elems: U2048, elem: U2048) -> (U2048, bool, U2048) {
+pub fn non_mem_wit_create(old_state: U2048, agg_elems: U2048, elem: U2048) -> (U2048, bool, U2048) {
+    return non_mem_wit_create_with_modulus(old_state, agg_elems, elem, U2048::from_dec_str(super::MODULUS).unwrap());
+}
+
+/// Equivalent to non_mem_wit_create for an arbitrary modulus.
+pub fn non_mem_wit_create_with_modulus(mut old_state: U2048, agg_elems: U2048, elem: U2048, modulus: U2048) -> (U2048, bool, U2048) {
     let pair = subroutines::bezout(agg_elems, elem).unwrap();
 
     if pair.sign_b {
-        old_state = subroutines::mod_inverse(old_state);
+        old_state = subroutines::mod_inverse_with_modulus(old_state, modulus);
     }
 
-    let B = subroutines::mod_exp(old_state, U2048::from(pair.coefficient_b), U2048::from_dec_str(super::MODULUS).unwrap());
+    let B = subroutines::mod_exp(old_state, U2048::from(pair.coefficient_b), modulus);
+    return (pair.coefficient_a, pair.sign_a, B);
+}
+
+/// Equivalent to non_mem_wit_create but computes B with mod_exp_ct.
+/// NOTE: Only the final exponentiation is constant-time. The Bezout computation and the inversion
+/// of "old_state" still branch on the coefficients.
+pub fn non_mem_wit_create_ct(mut old_state: U2048, agg_elems: U2048, elem: U2048) -> (U2048, bool, U2048) {
+    let modulus = U2048::from_dec_str(super::MODULUS).unwrap();
+    let pair = subroutines::bezout(agg_elems, elem).unwrap();
+
+    if pair.sign_b {
+        old_state = subroutines::mod_inverse_with_modulus(old_state, modulus);
+    }
+
+    let B = subroutines::mod_exp_ct(old_state, U2048::from(pair.coefficient_b), modulus);
     return (pair.coefficient_a, pair.sign_a, B);
 }
 
 /// Verifies a non-membership witness. "state" represents the current state.
-pub fn verify_non_mem_wit(old_state: U2048, mut state: U2048, witness: (U2048, bool, U2048), elem: U2048) -> bool {
+pub fn verify_non_mem_wit(old_state: U2048, state: U2048, witness: (U2048, bool, U2048), elem: U2048) -> bool {
+    return verify_non_mem_wit_with_modulus(old_state, state, witness, elem, U2048::from_dec_str(super::MODULUS).unwrap());
+}
+
+/// Equivalent to verify_non_mem_wit for an arbitrary modulus.
+pub fn verify_non_mem_wit_with_modulus(old_state: U2048, mut state: U2048, witness: (U2048, bool, U2048), elem: U2048, modulus: U2048) -> bool {
     let (a, sign_a, B) = witness;
 
     if sign_a {
-        state = subroutines::mod_inverse(state);
+        state = subroutines::mod_inverse_with_modulus(state, modulus);
     }
 
-    let exp_1 = subroutines::mod_exp(state, U2048::from(a), U2048::from_dec_str(super::MODULUS).unwrap());
-    let exp_2 = subroutines::mod_exp(B, elem, U2048::from_dec_str(super::MODULUS).unwrap());
+    let exp_1 = subroutines::mod_exp(state, U2048::from(a), modulus);
+    let exp_2 = subroutines::mod_exp(B, elem, modulus);
 
-    return subroutines::mul_mod(exp_1, exp_2, U2048::from_dec_str(super::MODULUS).unwrap()) == old_state;
+    return subroutines::mul_mod(exp_1, exp_2, modulus) == old_state;
 }
 
-pub fn update_non_mem_wit() {}
+/// Updates a non-membership witness based on untracked additions and deletions. "state" represents
+/// the state that the witness currently verifies against and additions are applied before deletions.
+/// Returns None if "elem" is not coprime to "additions". The updated witness continues to verify
+/// relative to the same reference generator. Note that "additions" represent the product of the
+/// added elements and "deletions" represents the product of the deleted elements.
+pub fn update_non_mem_wit(elem: U2048, witness: (U2048, bool, U2048), state: U2048, additions: U2048, deletions: U2048) -> Option<(U2048, bool, U2048)> {
+    let (mut a, mut sign_a, mut B) = witness;
+
+    // Handle added elems. If u*additions + v*elem = 1, then state^(a*v) can be folded into B.
+    if additions != U2048::from(1) {
+        let pair = subroutines::bezout(additions, elem)?;
+        let mut base = state;
+        if sign_a != pair.sign_b {
+            base = subroutines::mod_inverse(state);
+        }
+        B = subroutines::mul_mod(B, subroutines::mod_exp(base, a * pair.coefficient_b, U2048::from_dec_str(super::MODULUS).unwrap()),
+                                 U2048::from_dec_str(super::MODULUS).unwrap());
+        a = a * pair.coefficient_a;
+        sign_a = sign_a != pair.sign_a;
+    }
+
+    // Handle deleted elems
+    a = a * deletions;
+    return Some((a, sign_a, B));
+}
+
+/// Takes two elements + non-membership witnesses(relative to the same state and reference generator)
+/// and returns the aggregated non-membership witness for the product of the elements. Returns None
+/// if the elements are not coprime. Based on section 4.2 of https://eprint.iacr.org/2018/1188.pdf.
+pub fn agg_non_mem_wit(state: U2048, witness_x: (U2048, bool, U2048), witness_y: (U2048, bool, U2048), x: U2048, y: U2048) -> Option<(U2048, bool, U2048)> {
+    let (a_x, sign_x, B_x) = witness_x;
+    let (a_y, sign_y, B_y) = witness_y;
+    let pair = subroutines::bezout(x, y)?;
+    let modulus = U2048::from_dec_str(super::MODULUS).unwrap();
+
+    // If alpha*x + beta*y = 1, then B = B_y^alpha * B_x^beta and a = a_y*alpha*x + a_x*beta*y.
+    let base_y = if pair.sign_a { subroutines::mod_inverse(B_y) } else { B_y };
+    let base_x = if pair.sign_b { subroutines::mod_inverse(B_x) } else { B_x };
+    let mut B = subroutines::mul_mod(subroutines::mod_exp(base_y, pair.coefficient_a, modulus),
+                                     subroutines::mod_exp(base_x, pair.coefficient_b, modulus), modulus);
+    let (a, sign_a) = signed_add(a_y * pair.coefficient_a * x, sign_y != pair.sign_a,
+                                 a_x * pair.coefficient_b * y, sign_x != pair.sign_b);
+
+    // Reduce a modulo xy and fold the quotient into B.
+    let xy = x * y;
+    let base = if sign_a { subroutines::mod_inverse(state) } else { state };
+    B = subroutines::mul_mod(B, subroutines::mod_exp(base, a / xy, modulus), modulus);
+    return Some((a % xy, sign_a, B));
+}
+
+/// Adds two integers represented as (|a|, sign of a) and (|b|, sign of b).
+fn signed_add(a: U2048, sign_a: bool, b: U2048, sign_b: bool) -> (U2048, bool) {
+    if sign_a == sign_b {
+        return (a + b, sign_a);
+    }
+    if a >= b {
+        return (a - b, sign_a);
+    }
+    return (b - a, sign_b);
+}
 
 /// OPTIONAL FUNCTION.
 /// Given the current state, the previous state, the product of the added elements, and a subset of
@@ -117,6 +268,11 @@ mod tests {
         assert_eq!(mem_wit_create(U2048::from(2), U2048::from(1155), U2048::from(7)).unwrap(), U2048::from(5));
         assert_eq!(mem_wit_create(U2048::from(2), U2048::from(1155),U2048::from(11)).unwrap(), U2048::from(5));
         assert_eq!(mem_wit_create(U2048::from(2), U2048::from(1155),U2048::from(4)).is_none(), true);
+
+        for &elem in [3, 5, 7, 11, 4].iter() {
+            assert_eq!(mem_wit_create_ct(U2048::from(2), U2048::from(1155), U2048::from(elem)),
+                       mem_wit_create(U2048::from(2), U2048::from(1155), U2048::from(elem)));
+        }
     }
 
     #[test]
@@ -126,6 +282,27 @@ mod tests {
         assert_eq!(verify_agg_mem_wit(U2048::from(8), U2048::from(15), aggregate, proof), true);
     }
 
+    #[test]
+    fn test_aggregate_witnesses() {
+        let elems = [subroutines::hash_to_prime(&[0]), subroutines::hash_to_prime(&[1]), subroutines::hash_to_prime(&[2])];
+        let product = subroutines::prime_product(&elems);
+        let state = subroutines::mod_exp(U2048::from(2), product, U2048::from_dec_str(crate::MODULUS).unwrap());
+
+        let w: Vec<(Witness, U2048)> = elems.iter()
+            .map(|&elem| (Witness::MemWit(mem_wit_create(U2048::from(2), product, elem).unwrap()), elem))
+            .collect();
+        let aggregated = match aggregate_witnesses(&w).unwrap() {
+            Witness::MemWit(witness) => witness,
+            Witness::NonMemWit(_) => panic!(),
+        };
+        assert_eq!(verify_mem_wit(state, aggregated, product), true);
+        assert_eq!(verify_mem_wit(state, aggregated, elems[0] * elems[1]), false);
+
+        assert_eq!(aggregate_witnesses(&[]), None);
+        assert_eq!(aggregate_witnesses(&[w[0], w[0]]), None);
+        assert_eq!(aggregate_witnesses(&[w[0], (Witness::NonMemWit((U2048::from(1), false, U2048::from(1))), elems[1])]), None);
+    }
+
     #[test]
     fn test_verify_agg_mem_wit() {
         let proof = proofs::poe(U2048::from(2), U2048::from(12123), U2048::from(8));
@@ -145,6 +322,51 @@ mod tests {
         assert_eq!(update_mem_wit(elem, witness, new_state, additions, deletions), U2048::from(6));
     }
 
+    #[test]
+    fn test_update_mem_wit_on_add() {
+        let modulus = U2048::from_dec_str(crate::MODULUS).unwrap();
+        let (elem, added) = (U2048::from(3), U2048::from(5));
+        let witness = mem_wit_create(U2048::from(2), elem, elem).unwrap();
+        let new_state = subroutines::mod_exp(U2048::from(2), elem * added, modulus);
+
+        let new_witness = update_mem_wit_on_add(witness, added, modulus);
+        assert_eq!(verify_mem_wit(new_state, new_witness, elem), true);
+        assert_eq!(new_witness, update_mem_wit(elem, witness, new_state, added, U2048::from(1)));
+    }
+
+    #[test]
+    fn test_update_mem_wit_on_delete() {
+        let modulus = U2048::from_dec_str(crate::MODULUS).unwrap();
+        let elems = [U2048::from(3), U2048::from(5), U2048::from(7)];
+        let product = elems[0] * elems[1] * elems[2];
+        let witness = mem_wit_create(U2048::from(2), product, elems[0]).unwrap();
+        let new_state = subroutines::mod_exp(U2048::from(2), product / elems[1], modulus);
+
+        let new_witness = update_mem_wit_on_delete(elems[0], witness, new_state, elems[1], modulus).unwrap();
+        assert_eq!(verify_mem_wit(new_state, new_witness, elems[0]), true);
+
+        // The witnessed element was deleted
+        let new_state = subroutines::mod_exp(U2048::from(2), product / elems[0], modulus);
+        assert_eq!(update_mem_wit_on_delete(elems[0], witness, new_state, elems[0], modulus), None);
+    }
+
+    #[test]
+    fn test_disaggregate_mem_wit() {
+        let modulus = U2048::from_dec_str(crate::MODULUS).unwrap();
+        let product = U2048::from(3 * 5 * 7 * 11);
+        let state = subroutines::mod_exp(U2048::from(2), product, modulus);
+        let agg_witness = mem_wit_create(U2048::from(2), product, U2048::from(15)).unwrap();
+        assert_eq!(verify_mem_wit(state, agg_witness, U2048::from(15)), true);
+
+        let witness = disaggregate_mem_wit(agg_witness, U2048::from(5), modulus);
+        assert_eq!(witness, mem_wit_create(U2048::from(2), product, U2048::from(3)).unwrap());
+        assert_eq!(verify_mem_wit(state, witness, U2048::from(3)), true);
+
+        // 11 was not part of the aggregate, so the result does not verify
+        let witness = disaggregate_mem_wit(agg_witness, U2048::from(3), modulus);
+        assert_eq!(verify_mem_wit(state, witness, U2048::from(11)), false);
+    }
+
     #[test]
     fn test_create_all_mem_wit() {
         assert_eq!(create_all_mem_wit(U2048::from(2), &vec![U2048::from(3), U2048::from(5), U2048::from(7), U2048::from(11)]),
@@ -160,6 +382,38 @@ mod tests {
         assert_eq!(verify_non_mem_wit(U2048::from(2), U2048::from(5), (a, sign_a, B), U2048::from(11)), true);
         assert_eq!(verify_non_mem_wit(U2048::from(2), U2048::from(6), (a, sign_a, B), U2048::from(11)), false);
         assert_eq!(verify_non_mem_wit(U2048::from(2), U2048::from(5), (a, sign_a, B), U2048::from(5)), false);
+        assert_eq!(non_mem_wit_create_ct(U2048::from(2), U2048::from(105), U2048::from(11)), (a, sign_a, B));
+    }
+
+    #[test]
+    fn test_update_non_mem_wit() {
+        let witness = non_mem_wit_create(U2048::from(2), U2048::from(105), U2048::from(11));
+        let state = subroutines::mod_exp(U2048::from(2), U2048::from(105), U2048::from_dec_str(crate::MODULUS).unwrap());
+
+        // Add 19
+        let new_state = subroutines::mod_exp(state, U2048::from(19), U2048::from_dec_str(crate::MODULUS).unwrap());
+        let new_witness = update_non_mem_wit(U2048::from(11), witness, state, U2048::from(19), U2048::from(1)).unwrap();
+        assert_eq!(verify_non_mem_wit(U2048::from(2), new_state, new_witness, U2048::from(11)), true);
+
+        // Delete 5
+        let final_state = subroutines::mod_exp(U2048::from(2), U2048::from(21*19), U2048::from_dec_str(crate::MODULUS).unwrap());
+        let final_witness = update_non_mem_wit(U2048::from(11), new_witness, new_state, U2048::from(1), U2048::from(5)).unwrap();
+        assert_eq!(verify_non_mem_wit(U2048::from(2), final_state, final_witness, U2048::from(11)), true);
+
+        // Cannot add an element that shares a factor with the witnessed element
+        assert_eq!(update_non_mem_wit(U2048::from(11), witness, state, U2048::from(22), U2048::from(1)), None);
+    }
+
+    #[test]
+    fn test_agg_non_mem_wit() {
+        let state = subroutines::mod_exp(U2048::from(2), U2048::from(105), U2048::from_dec_str(crate::MODULUS).unwrap());
+        let witness_x = non_mem_wit_create(U2048::from(2), U2048::from(105), U2048::from(11));
+        let witness_y = non_mem_wit_create(U2048::from(2), U2048::from(105), U2048::from(17));
+
+        let aggregated = agg_non_mem_wit(state, witness_x, witness_y, U2048::from(11), U2048::from(17)).unwrap();
+        assert_eq!(verify_non_mem_wit(U2048::from(2), state, aggregated, U2048::from(187)), true);
+
+        assert_eq!(agg_non_mem_wit(state, witness_x, witness_x, U2048::from(11), U2048::from(11)), None);
     }
 
     #[test]
@@ -0,0 +1,15 @@
+#!/usr/bin/env bash
+
+set -e
+
+# Fails if a std-only path leaks into the crates that are compiled into the runtime.
+cd "$(dirname "$0")/.."
+
+echo "*** Checking the accumulator without std"
+cargo check --manifest-path accumulator/Cargo.toml --no-default-features
+
+echo "*** Checking the vector commitment without std"
+cargo check --manifest-path vector-commitment/Cargo.toml --no-default-features
+
+echo "*** Checking the accumulator for the wasm target"
+cargo +nightly check --manifest-path accumulator/Cargo.toml --no-default-features --target wasm32-unknown-unknown
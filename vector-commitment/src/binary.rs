@@ -5,27 +5,201 @@ use accumulator::*;
 use accumulator::witnesses;
 use rstd::prelude::Vec;
 use runtime_io;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "metrics")]
+use std::time::Instant;
 
 /// Commit a vector of bits(represented as bool array) to an accumulator. The second value of
 /// the returned tuple is the product of the accumulated elements.
 /// NOTE: In the stateless blockchain model, after the validator commits the vector to the accumulator,
 /// users should immediately request membership witnesses for their committed bit using the returned "product" value.
+/// With the "parallel" feature enabled, the elements are hashed to primes concurrently.
 pub fn commit(accumulator: U2048, values: &[bool], indices: &[usize]) -> (U2048, U2048) {
-    let elems: Vec<U2048> = values
+    let (state, product, _) = commit_with_proof(accumulator, values, indices);
+    return (state, product);
+}
+
+/// Equivalent to commit but also returns a proof of exponentiation that state = accumulator^product.
+pub fn commit_with_proof(accumulator: U2048, values: &[bool], indices: &[usize]) -> (U2048, U2048, U2048) {
+    #[cfg(feature = "parallel")]
+    let elems = par_get_commit_elems(values, indices);
+    #[cfg(not(feature = "parallel"))]
+    let elems = get_commit_elems(values, indices);
+
+    // Equivalent to batch_add but with windowed exponentiation for the large product.
+    let product = subroutines::prime_product(&elems);
+    let state = subroutines::mod_exp_windowed(accumulator, product, U2048::from_dec_str(MODULUS).unwrap());
+    let proof = proofs::poe(accumulator, product, state);
+    return (state, product, proof);
+}
+
+/// Equivalent to commit in an arbitrary group of unknown order.
+pub fn commit_in<G: group::Group>(accumulator: G::Elem, values: &[bool], indices: &[usize]) -> (G::Elem, U2048) {
+    let product = subroutines::prime_product(&get_commit_elems(values, indices));
+    return (G::exp(accumulator, product), product);
+}
+
+/// Per-phase timings(in nanoseconds) of an instrumented commit, open or verify: hashing the indices
+/// to primes, building the products of the primes, and the modular exponentiations.
+#[cfg(feature = "metrics")]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct CommitStats {
+    pub primes_ns: u128,
+    pub product_ns: u128,
+    pub exp_ns: u128,
+}
+
+/// Equivalent to commit but also returns the time spent in each phase.
+#[cfg(feature = "metrics")]
+pub fn commit_instrumented(accumulator: U2048, values: &[bool], indices: &[usize]) -> ((U2048, U2048), CommitStats) {
+    let start = Instant::now();
+    #[cfg(feature = "parallel")]
+    let elems = par_get_commit_elems(values, indices);
+    #[cfg(not(feature = "parallel"))]
+    let elems = get_commit_elems(values, indices);
+    let primes_ns = start.elapsed().as_nanos();
+
+    let start = Instant::now();
+    let product = subroutines::prime_product(&elems);
+    let product_ns = start.elapsed().as_nanos();
+
+    let start = Instant::now();
+    let state = subroutines::mod_exp_windowed(accumulator, product, U2048::from_dec_str(MODULUS).unwrap());
+    let exp_ns = start.elapsed().as_nanos();
+    return ((state, product), CommitStats { primes_ns, product_ns, exp_ns });
+}
+
+/// Equivalent to batch_open but also returns the time spent in each phase. Witness creation(including
+/// the Bezout coefficients) is counted as exponentiation.
+#[cfg(feature = "metrics")]
+pub fn batch_open_instrumented(old_state: U2048, agg: U2048, b: &[bool], i: &[usize]) -> ((Witness, Witness), CommitStats) {
+    let start = Instant::now();
+    #[cfg(feature = "parallel")]
+    let (ones, zeros) = par_get_bit_primes(b, i);
+    #[cfg(not(feature = "parallel"))]
+    let (ones, zeros) = get_bit_primes(b, i);
+    let primes_ns = start.elapsed().as_nanos();
+
+    let start = Instant::now();
+    let (p_ones, p_zeros) = (subroutines::prime_product(&ones), subroutines::prime_product(&zeros));
+    let product_ns = start.elapsed().as_nanos();
+
+    let start = Instant::now();
+    let pi_inclusion = Witness::MemWit(witnesses::mem_wit_create(old_state, agg, p_ones).unwrap());
+    let pi_exclusion = Witness::NonMemWit(witnesses::non_mem_wit_create(old_state, agg, p_zeros));
+    let exp_ns = start.elapsed().as_nanos();
+    return ((pi_inclusion, pi_exclusion), CommitStats { primes_ns, product_ns, exp_ns });
+}
+
+/// Equivalent to batch_verify but also returns the time spent in each phase.
+#[cfg(feature = "metrics")]
+pub fn batch_verify_instrumented(old_state: U2048, accumulator: U2048, b: &[bool], i: &[usize], pi_i: Witness, pi_e: Witness) -> (bool, CommitStats) {
+    let start = Instant::now();
+    #[cfg(feature = "parallel")]
+    let (ones, zeros) = par_get_bit_primes(b, i);
+    #[cfg(not(feature = "parallel"))]
+    let (ones, zeros) = get_bit_primes(b, i);
+    let primes_ns = start.elapsed().as_nanos();
+
+    let start = Instant::now();
+    let (p_ones, p_zeros) = (subroutines::prime_product(&ones), subroutines::prime_product(&zeros));
+    let product_ns = start.elapsed().as_nanos();
+
+    let start = Instant::now();
+    let result = verify_bit_elems(old_state, accumulator, p_ones, p_zeros, pi_i, pi_e);
+    let exp_ns = start.elapsed().as_nanos();
+    return (result, CommitStats { primes_ns, product_ns, exp_ns });
+}
+
+/// Domain separation tag for the bit indices of a vector commitment(see hash_index).
+pub const VC_TAG: &[u8] = b"stateless-blockchain/vc/index";
+
+/// Hashes a bit index to its accumulator element. The index is tagged with VC_TAG so that its prime
+/// differs from the prime of the same integer hashed in another context. The index is hashed as a u64
+/// in little endian regardless of the width of usize, so the wasm runtime and native code derive the
+/// same prime.
+pub fn hash_index(index: usize) -> U2048 {
+    return hash_index_tagged(VC_TAG, index);
+}
+
+/// Equivalent to subroutines::hash_to_prime_tagged(tag, &(index as u64).to_le_bytes()) but builds the
+/// tagged input in a fixed stack buffer instead of a Vec, so hashing an index does not allocate(see
+/// vc::verify_at_key_noalloc). Assumes that the tag is at most 52 bytes long.
+fn hash_index_tagged(tag: &[u8], index: usize) -> U2048 {
+    let mut data: [u8; 64] = [0; 64];
+    let len = 4 + tag.len() + 8;
+    data[..4].copy_from_slice(&(tag.len() as u32).to_le_bytes());
+    data[4..4 + tag.len()].copy_from_slice(tag);
+    data[4 + tag.len()..len].copy_from_slice(&(index as u64).to_le_bytes());
+    return subroutines::hash_to_prime(&data[..len]);
+}
+
+/// Hashes the indices of the set bits to primes.
+fn get_commit_elems(values: &[bool], indices: &[usize]) -> Vec<U2048> {
+    return values
         .into_iter()
         .enumerate()
         .filter(|(_, val)| **val)
-        .map(|(index, _)| subroutines::hash_to_prime(&indices[index].to_le_bytes()))
+        .map(|(index, _)| hash_index(indices[index]))
         .collect();
-    let (state, product, _) = batch_add(accumulator, &elems);
+}
+
+/// Parallel equivalent of get_commit_elems. The order of the returned elements is preserved.
+#[cfg(feature = "parallel")]
+fn par_get_commit_elems(values: &[bool], indices: &[usize]) -> Vec<U2048> {
+    return values
+        .par_iter()
+        .enumerate()
+        .filter(|(_, val)| **val)
+        .map(|(index, _)| hash_index(indices[index]))
+        .collect();
+}
+
+/// Commit a sparse bit vector represented only by the indices of its set bits. Equivalent to commit
+/// on the dense vector, but the zero bits never need to be materialized.
+pub fn commit_sparse(accumulator: U2048, set_indices: &[usize]) -> (U2048, U2048) {
+    let (state, product, _) = batch_add(accumulator, &get_sparse_elems(set_indices));
     return (state, product);
 }
 
+/// Hashes the indices of the set bits of a sparse bit vector to primes.
+fn get_sparse_elems(set_indices: &[usize]) -> Vec<U2048> {
+    return set_indices
+        .iter()
+        .map(|index| hash_index(*index))
+        .collect();
+}
+
+/// Create an opening for a set of set bits of a sparse commitment. The current state of the
+/// accumulator should equal "old_state" raised to the "agg" power.
+/// NOTE: The opening only proves that the bits are set. Use batch_open to also prove that the
+/// remaining bits are zero.
+pub fn open_sparse(old_state: U2048, agg: U2048, set_indices: &[usize]) -> Option<Witness> {
+    let p_ones = subroutines::prime_product(&get_sparse_elems(set_indices));
+    return witnesses::mem_wit_create(old_state, agg, p_ones).map(Witness::MemWit);
+}
+
+/// Verifies an opening produced by open_sparse.
+pub fn verify_sparse(accumulator: U2048, set_indices: &[usize], proof: Witness) -> bool {
+    let p_ones = subroutines::prime_product(&get_sparse_elems(set_indices));
+    match proof {
+        Witness::MemWit(witness) => {
+            return witnesses::verify_mem_wit(accumulator, witness, p_ones);
+        },
+        Witness::NonMemWit(_) => {
+            return false;
+        },
+    }
+}
+
 /// Create an opening for a bit commitment. The current state of the accumulator should equal
 /// "old_state" raised to the "agg" power(product of aggregated elements) where the committed bit
 /// is contained in "agg".
 pub fn open(old_state: U2048, bit: bool, index: usize, agg: U2048) -> Witness {
-    let elem = subroutines::hash_to_prime(&index.to_le_bytes());
+    let elem = hash_index(index);
     if bit {
         return Witness::MemWit(witnesses::mem_wit_create(old_state, agg, elem).unwrap());
     }
@@ -36,7 +210,7 @@ pub fn open(old_state: U2048, bit: bool, index: usize, agg: U2048) -> Witness {
 
 /// Verify a membership/non-membership proof (produced by an opening) for a given bit commitment.
 pub fn verify(old_state: U2048, accumulator: U2048, bit: bool, index: usize, proof: Witness) -> bool {
-    let elem = subroutines::hash_to_prime(&index.to_le_bytes());
+    let elem = hash_index(index);
     if bit {
         match proof {
             Witness::MemWit(witness) => {
@@ -60,37 +234,130 @@ pub fn verify(old_state: U2048, accumulator: U2048, bit: bool, index: usize, pro
 }
 
 /// Given a bit array and an array of corresponding indices, outputs the product of the "ones"
-/// elements and the product of the "zeros" elements.
+/// elements and the product of the "zeros" elements. With the "parallel" feature enabled, the indices
+/// are hashed to primes concurrently.
 pub fn get_bit_elems(b: &[bool], i: &[usize]) -> (U2048, U2048) {
-    let ones_indices: Vec<usize> = b
+    #[cfg(feature = "parallel")]
+    let (ones, zeros) = par_get_bit_primes(b, i);
+    #[cfg(not(feature = "parallel"))]
+    let (ones, zeros) = get_bit_primes(b, i);
+
+    let p_ones = subroutines::prime_product(&ones);
+    let p_zeros = subroutines::prime_product(&zeros);
+    return (p_ones, p_zeros)
+}
+
+/// Hashes the indices of the "ones" and "zeros" bits to primes.
+fn get_bit_primes(b: &[bool], i: &[usize]) -> (Vec<U2048>, Vec<U2048>) {
+    let ones: Vec<U2048> = b
         .into_iter()
         .enumerate()
         .filter(|(_, bit)| **bit)
-        .map(|(index, _)| index)
+        .map(|(index, _)| hash_index(i[index]))
         .collect();
 
-    let zeros_indices: Vec<usize> = b
+    let zeros: Vec<U2048> = b
         .into_iter()
         .enumerate()
         .filter(|(_, bit)| !**bit)
-        .map(|(index, _)| index)
+        .map(|(index, _)| hash_index(i[index]))
         .collect();
 
-    let ones: Vec<U2048> = ones_indices
-        .into_iter()
+    return (ones, zeros);
+}
+
+/// Parallel equivalent of get_bit_primes. The order of the returned elements is preserved.
+#[cfg(feature = "parallel")]
+fn par_get_bit_primes(b: &[bool], i: &[usize]) -> (Vec<U2048>, Vec<U2048>) {
+    let ones: Vec<U2048> = b
+        .par_iter()
         .enumerate()
-        .map(|(_, index)| subroutines::hash_to_prime(&(i[index]).to_le_bytes()))
+        .filter(|(_, bit)| **bit)
+        .map(|(index, _)| hash_index(i[index]))
         .collect();
 
-    let zeros: Vec<U2048> = zeros_indices
-        .into_iter()
+    let zeros: Vec<U2048> = b
+        .par_iter()
         .enumerate()
-        .map(|(_, index)| subroutines::hash_to_prime(&(i[index]).to_le_bytes()))
+        .filter(|(_, bit)| !**bit)
+        .map(|(index, _)| hash_index(i[index]))
         .collect();
 
-    let p_ones = subroutines::prime_product(&ones);
-    let p_zeros = subroutines::prime_product(&zeros);
-    return (p_ones, p_zeros)
+    return (ones, zeros);
+}
+
+/// Caches the primes that bit indices hash to. Hashing to a prime dominates the cost of committing,
+/// opening and verifying, so indices that appear in many operations should only be hashed once.
+/// The cache is Send + Sync and can be shared between threads behind a Mutex.
+#[cfg(feature = "std")]
+#[derive(Default)]
+pub struct PrimeCache {
+    primes: HashMap<usize, U2048>,
+}
+
+#[cfg(feature = "std")]
+impl PrimeCache {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Returns the prime for an index, hashing it only if it has not been seen before.
+    pub fn hash_index_cached(&mut self, index: usize) -> U2048 {
+        return *self.primes.entry(index).or_insert_with(|| hash_index(index));
+    }
+
+    /// Hashes every index that is not yet cached. With the "parallel" feature enabled, the missing
+    /// indices are hashed concurrently.
+    pub fn fill(&mut self, indices: &[usize]) {
+        let missing: Vec<usize> = indices.iter().cloned().filter(|index| !self.primes.contains_key(index)).collect();
+        #[cfg(feature = "parallel")]
+        let primes: Vec<U2048> = missing.par_iter().map(|index| hash_index(*index)).collect();
+        #[cfg(not(feature = "parallel"))]
+        let primes: Vec<U2048> = missing.iter().map(|index| hash_index(*index)).collect();
+        self.primes.extend(missing.into_iter().zip(primes.into_iter()));
+    }
+
+    /// Number of cached primes.
+    pub fn len(&self) -> usize {
+        return self.primes.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.primes.is_empty();
+    }
+}
+
+/// Equivalent to get_bit_elems but consults the cache before hashing an index.
+#[cfg(feature = "std")]
+pub fn get_bit_elems_cached(b: &[bool], i: &[usize], cache: &mut PrimeCache) -> (U2048, U2048) {
+    let mut p_ones = U2048::from(1);
+    let mut p_zeros = U2048::from(1);
+    for (bit, index) in b.iter().zip(i.iter()) {
+        let elem = cache.hash_index_cached(*index);
+        if *bit {
+            p_ones *= elem;
+        }
+        else {
+            p_zeros *= elem;
+        }
+    }
+    return (p_ones, p_zeros);
+}
+
+/// Equivalent to commit but consults the cache before hashing the indices of the set bits.
+#[cfg(feature = "std")]
+pub fn commit_with_cache(cache: &mut PrimeCache, accumulator: U2048, values: &[bool], indices: &[usize]) -> (U2048, U2048) {
+    let set_indices: Vec<usize> = values
+        .iter()
+        .zip(indices.iter())
+        .filter(|(val, _)| **val)
+        .map(|(_, index)| *index)
+        .collect();
+    cache.fill(&set_indices);
+
+    let elems: Vec<U2048> = set_indices.into_iter().map(|index| cache.hash_index_cached(index)).collect();
+    let (state, product, _) = batch_add(accumulator, &elems);
+    return (state, product);
 }
 
 /// Batch opens a set of bit commitments. The accumulated values of the commitments must be contained in
@@ -107,8 +374,18 @@ pub fn batch_open(old_state: U2048, agg: U2048, b: &[bool], i: &[usize]) -> (Wit
 
 /// Verifies a set of membership and non-membership witnesses for a set of bit commitments.
 /// This function has been slightly modified from the original specification. See page 20 of the paper for more info.
+/// Non-canonical witnesses(see Witness::validate) are rejected.
 pub fn batch_verify(old_state: U2048, accumulator: U2048, b: &[bool], i: &[usize], pi_i: Witness, pi_e: Witness) -> bool {
     let (p_ones, p_zeros) = get_bit_elems(b, i);
+    return verify_bit_elems(old_state, accumulator, p_ones, p_zeros, pi_i, pi_e);
+}
+
+/// Equivalent to batch_verify given the products of the "ones" and "zeros" elements.
+fn verify_bit_elems(old_state: U2048, accumulator: U2048, p_ones: U2048, p_zeros: U2048, pi_i: Witness, pi_e: Witness) -> bool {
+    let modulus = U2048::from_dec_str(MODULUS).unwrap();
+    if !pi_i.validate(modulus) || !pi_e.validate(modulus) {
+        return false;
+    }
 
     let ver_mem_result;
     match pi_i {
@@ -132,6 +409,123 @@ pub fn batch_verify(old_state: U2048, accumulator: U2048, b: &[bool], i: &[usize
     return ver_mem_result && ver_non_mem_result;
 }
 
+/// Domain separation tag for the elements of the zero bits in CommitMode::BothPolarities.
+pub const VC_ZERO_TAG: &[u8] = b"stateless-blockchain/vc/zero-index";
+
+/// Selects which bits of a vector are accumulated.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CommitMode {
+    /// Only the set bits are accumulated(see commit). The zero bits are proven by a non-membership
+    /// witness for their elements.
+    SetBits,
+    /// Every bit is accumulated with an element tagged by the value of the bit(see hash_index_with_bit),
+    /// so each bit is bound by a membership witness. An opening also proves non-membership of the
+    /// elements of the opposite values, so that an index committed with both values is rejected.
+    BothPolarities,
+}
+
+/// Hashes a bit index together with the value of the bit. The element of a set bit equals hash_index
+/// so that both modes agree on the set bits.
+pub fn hash_index_with_bit(index: usize, bit: bool) -> U2048 {
+    if bit {
+        return hash_index(index);
+    }
+    return hash_index_tagged(VC_ZERO_TAG, index);
+}
+
+/// Returns the product of the elements that must be accumulated and the product of the elements that
+/// must be absent for the bits to open under "mode". With CommitMode::SetBits, this equals
+/// get_bit_elems.
+pub fn get_mode_elems(b: &[bool], i: &[usize], mode: CommitMode) -> (U2048, U2048) {
+    match mode {
+        CommitMode::SetBits => return get_bit_elems(b, i),
+        CommitMode::BothPolarities => {
+            let present: Vec<U2048> = b.iter().zip(i.iter()).map(|(bit, index)| hash_index_with_bit(*index, *bit)).collect();
+            let absent: Vec<U2048> = b.iter().zip(i.iter()).map(|(bit, index)| hash_index_with_bit(*index, !*bit)).collect();
+            return (subroutines::prime_product(&present), subroutines::prime_product(&absent));
+        },
+    }
+}
+
+/// Equivalent to commit but accumulates the bits according to "mode".
+pub fn commit_with_mode(accumulator: U2048, values: &[bool], indices: &[usize], mode: CommitMode) -> (U2048, U2048) {
+    match mode {
+        CommitMode::SetBits => return commit(accumulator, values, indices),
+        CommitMode::BothPolarities => {
+            let (product, _) = get_mode_elems(values, indices, mode);
+            return (subroutines::mod_exp(accumulator, product, U2048::from_dec_str(MODULUS).unwrap()), product);
+        },
+    }
+}
+
+/// Equivalent to batch_open for a commitment produced by commit_with_mode. Returns None if the bits
+/// are not committed under "mode".
+pub fn batch_open_with_mode(old_state: U2048, agg: U2048, b: &[bool], i: &[usize], mode: CommitMode) -> Option<(Witness, Witness)> {
+    let (p_present, p_absent) = get_mode_elems(b, i, mode);
+    let mem_wit = witnesses::mem_wit_create(old_state, agg, p_present)?;
+    subroutines::bezout(agg, p_absent)?;
+    let non_mem_wit = witnesses::non_mem_wit_create(old_state, agg, p_absent);
+    return Some((Witness::MemWit(mem_wit), Witness::NonMemWit(non_mem_wit)));
+}
+
+/// Equivalent to batch_verify for a commitment produced by commit_with_mode.
+pub fn batch_verify_with_mode(old_state: U2048, accumulator: U2048, b: &[bool], i: &[usize], pi_i: Witness, pi_e: Witness, mode: CommitMode) -> bool {
+    let (p_present, p_absent) = get_mode_elems(b, i, mode);
+    return verify_bit_elems(old_state, accumulator, p_present, p_absent, pi_i, pi_e);
+}
+
+/// Non-membership witness whose exponent is replaced by a proof of knowledge of that exponent,
+/// represented as (B, sign of the exponent, proof).
+pub type PokeNonMemWit = (U2048, bool, proofs::PokeProof);
+
+/// Equivalent to batch_open but the exclusion proof contains a PoKE instead of the raw Bezout
+/// exponent, which grows with the product of the aggregated elements. The size of the returned
+/// proofs is therefore independent of the number of opened bits.
+pub fn batch_open_poke(old_state: U2048, agg: U2048, b: &[bool], i: &[usize]) -> (Witness, PokeNonMemWit) {
+    let (p_ones, p_zeros) = get_bit_elems(b, i);
+    let pi_inclusion = Witness::MemWit(witnesses::mem_wit_create(old_state, agg, p_ones).unwrap());
+
+    // Prove knowledge of "a" such that state^a = old_state * B^(-p_zeros)
+    let (a, sign_a, B) = witnesses::non_mem_wit_create(old_state, agg, p_zeros);
+    let mut state = subroutines::mod_exp(old_state, agg, U2048::from_dec_str(MODULUS).unwrap());
+    if sign_a {
+        state = subroutines::mod_inverse(state);
+    }
+    let result = subroutines::mod_exp(state, a, U2048::from_dec_str(MODULUS).unwrap());
+    let pi_exclusion = (B, sign_a, proofs::poke(state, a, result));
+
+    return (pi_inclusion, pi_exclusion);
+}
+
+/// Verifies a set of proofs produced by batch_open_poke. Like the other verifiers, rejects proofs
+/// whose group elements are not canonical residues(see Witness::validate).
+pub fn batch_verify_poke(old_state: U2048, mut accumulator: U2048, b: &[bool], i: &[usize], pi_i: Witness, pi_e: PokeNonMemWit) -> bool {
+    let modulus = U2048::from_dec_str(MODULUS).unwrap();
+    let (B, sign_a, (z, Q, r)) = pi_e;
+    if !pi_i.validate(modulus) || B >= modulus || z >= modulus || Q >= modulus {
+        return false;
+    }
+    let (p_ones, p_zeros) = get_bit_elems(b, i);
+
+    match pi_i {
+        Witness::MemWit(mem_wit) => {
+            if !witnesses::verify_mem_wit(accumulator, mem_wit, p_ones) {
+                return false;
+            }
+        },
+        Witness::NonMemWit(_) => {
+            return false;
+        },
+    }
+
+    if sign_a {
+        accumulator = subroutines::mod_inverse(accumulator);
+    }
+    let B_inv = subroutines::mod_exp(subroutines::mod_inverse(B), p_zeros, modulus);
+    let result = subroutines::mul_mod(old_state, B_inv, modulus);
+    return proofs::verify_poke(accumulator, result, z, Q, r);
+}
+
 /// Updates a segment of a vector commitment. Assumes that an honest party performs the update.
 /// Arguments:
 /// - accumulator: The current state of the accumulator.
@@ -156,6 +550,26 @@ pub fn update(accumulator: U2048, old_state: U2048, agg: U2048, b: &[bool], i: &
 #[cfg(test)]
 mod tests {
     use super::*;
+    use codec::Encode;
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_instrumented() {
+        let accumulator = U2048::from(2);
+        let values = [true, false, true, true, false];
+        let indices = [0, 1, 2, 3, 4];
+        let ((state, product), stats) = commit_instrumented(accumulator, &values, &indices);
+        assert_eq!((state, product), commit(accumulator, &values, &indices));
+        assert_eq!(stats.primes_ns > 0 && stats.exp_ns > 0, true);
+
+        let ((pi_i, pi_e), stats) = batch_open_instrumented(accumulator, product, &values[..2], &indices[..2]);
+        assert_eq!((pi_i, pi_e), batch_open(accumulator, product, &values[..2], &indices[..2]));
+        assert_eq!(stats.primes_ns > 0, true);
+
+        let (result, stats) = batch_verify_instrumented(accumulator, state, &values[..2], &indices[..2], pi_i, pi_e);
+        assert_eq!(result, true);
+        assert_eq!(stats.primes_ns > 0 && stats.exp_ns > 0, true);
+    }
 
     #[test]
     fn test_open_and_verify() {
@@ -165,8 +579,8 @@ mod tests {
         let (state, product) = commit(accumulator, &arr, &[0, 1, 2]);
 
         // Check commit
-        let h_0 = subroutines::hash_to_prime(&(0 as usize).to_le_bytes());
-        let h_2 = subroutines::hash_to_prime(&(2 as usize).to_le_bytes());
+        let h_0 = hash_index(0);
+        let h_2 = hash_index(2);
         assert_eq!(subroutines::mod_exp(accumulator, h_0*h_2, U2048::from_dec_str(MODULUS).unwrap()), state);
 
         // Open at two indices
@@ -183,14 +597,148 @@ mod tests {
         assert_eq!(verify(accumulator, state, true, 2, open_1), false);
     }
 
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_commit() {
+        let accumulator = U2048::from(2);
+        let values: Vec<bool> = (0..60).map(|index| index % 3 != 0).collect();
+        let indices: Vec<usize> = (0..60).collect();
+
+        let elems = get_commit_elems(&values, &indices);
+        assert_eq!(par_get_commit_elems(&values, &indices), elems);
+
+        let (state, product, _) = batch_add(accumulator, &elems);
+        assert_eq!(commit(accumulator, &values, &indices), (state, product));
+
+        let (ones, zeros) = get_bit_primes(&values, &indices);
+        assert_eq!(get_bit_elems(&values, &indices), (subroutines::prime_product(&ones), subroutines::prime_product(&zeros)));
+
+        // The products of a 1024 bit vector overflow U2048 so only the primes are compared.
+        let values: Vec<bool> = (0..1024).map(|index| index % 5 < 2).collect();
+        let indices: Vec<usize> = (0..1024).collect();
+        assert_eq!(par_get_bit_primes(&values, &indices), get_bit_primes(&values, &indices));
+        assert_eq!(par_get_commit_elems(&values, &indices), get_commit_elems(&values, &indices));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_prime_cache() {
+        let mut cache = PrimeCache::new();
+        for index in 0..32 {
+            assert_eq!(cache.hash_index_cached(index), hash_index(index));
+        }
+        assert_eq!(cache.len(), 32);
+
+        // Repeated indices are served from the cache
+        for index in 0..32 {
+            assert_eq!(cache.hash_index_cached(index), hash_index(index));
+        }
+        assert_eq!(cache.len(), 32);
+
+        let arr: [bool; 4] = [true, false, false, true];
+        let indices = [3, 40, 7, 41];
+        assert_eq!(get_bit_elems_cached(&arr, &indices, &mut cache), get_bit_elems(&arr, &indices));
+        assert_eq!(cache.len(), 34);
+    }
+
+    #[test]
+    fn test_batch_open_poke() {
+        // One bit and 100 bits, half of them set. U2048 overflow prevents much larger batches.
+        let accumulator = U2048::from(2);
+        let small: Vec<bool> = vec![true];
+        let large: Vec<bool> = (0..100).map(|j| j % 2 == 1).collect();
+
+        let mut sizes = Vec::new();
+        for b in [small, large].iter() {
+            let i: Vec<usize> = (0..b.len()).collect();
+            let (state, product) = commit(accumulator, b, &i);
+            let (pi_i, pi_e) = batch_open_poke(accumulator, product, b, &i);
+            assert_eq!(batch_verify_poke(accumulator, state, b, &i, pi_i, pi_e), true);
+
+            // Bit 0 flipped
+            let mut tampered = b.clone();
+            tampered[0] = !tampered[0];
+            assert_eq!(batch_verify_poke(accumulator, state, &tampered, &i, pi_i, pi_e), false);
+
+            // Non-canonical group elements are rejected
+            let modulus = U2048::from_dec_str(MODULUS).unwrap();
+            let (B, sign_a, (z, Q, r)) = pi_e;
+            assert_eq!(batch_verify_poke(accumulator, state, b, &i, pi_i, (B + modulus, sign_a, (z, Q, r))), false);
+            assert_eq!(batch_verify_poke(accumulator, state, b, &i, pi_i, (B, sign_a, (z, Q + modulus, r))), false);
+            if let Witness::MemWit(mem_wit) = pi_i {
+                assert_eq!(batch_verify_poke(accumulator, state, b, &i, Witness::MemWit(mem_wit + modulus), pi_e), false);
+            }
+
+            sizes.push((pi_i, pi_e).encode().len());
+        }
+        assert_eq!(sizes[0], sizes[1]);
+    }
+
+    #[test]
+    fn test_commit_with_proof() {
+        let accumulator = U2048::from(2);
+        let arr: [bool; 6] = [true, false, true, true, false, true];
+        let indices = [0, 1, 2, 3, 4, 5];
+
+        // Equivalent to batch_add of the elements of the set bits
+        let (state, product, proof) = commit_with_proof(accumulator, &arr, &indices);
+        assert_eq!(batch_add(accumulator, &get_commit_elems(&arr, &indices)), (state, product, proof));
+        assert_eq!(proofs::verify_poe(accumulator, product, state, proof), true);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_commit_with_cache() {
+        let accumulator = U2048::from(2);
+        let arr: [bool; 6] = [true, false, true, true, false, true];
+        let indices = [0, 1, 2, 3, 4, 5];
+
+        let mut cache = PrimeCache::new();
+        let cold = commit_with_cache(&mut cache, accumulator, &arr, &indices);
+        assert_eq!(cold, commit(accumulator, &arr, &indices));
+        assert_eq!(cache.len(), 4);
+
+        // A warm cache produces the same commitment and the same primes as a cold call
+        assert_eq!(commit_with_cache(&mut cache, accumulator, &arr, &indices), cold);
+        assert_eq!(cache.len(), 4);
+        for &index in indices.iter() {
+            assert_eq!(cache.hash_index_cached(index), hash_index(index));
+        }
+
+        cache.fill(&[0, 6, 7]);
+        assert_eq!(cache.len(), 8);
+    }
+
+    #[test]
+    fn test_sparse() {
+        let accumulator = U2048::from(2);
+        let mut dense = [false; 16];
+        dense[3] = true;
+        dense[11] = true;
+        let indices: Vec<usize> = (0..16).collect();
+
+        let (state, product) = commit_sparse(accumulator, &[3, 11]);
+        assert_eq!((state, product), commit(accumulator, &dense, &indices));
+        let (p_ones, p_zeros) = get_bit_elems(&dense, &indices);
+        assert_eq!(product < p_ones * p_zeros, true);
+
+        let proof = open_sparse(accumulator, product, &[3, 11]).unwrap();
+        assert_eq!(verify_sparse(state, &[3, 11], proof), true);
+        assert_eq!(verify_sparse(state, &[0, 3, 11], proof), false);
+        assert_eq!(open_sparse(accumulator, product, &[0, 3]), None);
+
+        // An all-ones vector matches the dense path
+        assert_eq!(commit_sparse(accumulator, &indices), commit(accumulator, &[true; 16], &indices));
+    }
+
     #[test]
     fn test_get_bit_elems() {
         let arr: [bool; 3] = [false, false, true];
         let indices = [0, 1, 5];
 
-        let h_0 = subroutines::hash_to_prime(&(0 as usize).to_le_bytes());
-        let h_1 = subroutines::hash_to_prime(&(1 as usize).to_le_bytes());
-        let h_5 = subroutines::hash_to_prime(&(5 as usize).to_le_bytes());
+        let h_0 = hash_index(0);
+        let h_1 = hash_index(1);
+        let h_5 = hash_index(5);
 
         let (p_ones, p_zeros) = get_bit_elems(&arr, &indices);
         assert_eq!(p_ones, h_5);
@@ -205,10 +753,10 @@ mod tests {
 
         let (i, e) = batch_open(accumulator, product, &[true, false, false, true], &[0, 3, 4, 5]);
 
-        let h_0 = subroutines::hash_to_prime(&(0 as usize).to_le_bytes());
-        let h_3 = subroutines::hash_to_prime(&(3 as usize).to_le_bytes());
-        let h_4 = subroutines::hash_to_prime(&(4 as usize).to_le_bytes());
-        let h_5 = subroutines::hash_to_prime(&(5 as usize).to_le_bytes());
+        let h_0 = hash_index(0);
+        let h_3 = hash_index(3);
+        let h_4 = hash_index(4);
+        let h_5 = hash_index(5);
 
         // Manual check of openings
         let ones_product = subroutines::prime_product(&vec![h_0, h_5]);
@@ -235,15 +783,54 @@ mod tests {
         assert_eq!(batch_verify(accumulator, state, &[true, false, false, true], &[0, 3, 4, 5], i, e), true);
     }
 
+    #[test]
+    fn test_hash_index() {
+        // Fixed expected primes so that a platform-dependent index width is caught
+        assert_eq!(hash_index(0), U2048::from(67987727));
+        assert_eq!(hash_index(1), U2048::from(2295145987u32));
+        assert_eq!(hash_index(255), U2048::from(2261224151u32));
+        assert_eq!(hash_index(1), subroutines::hash_to_prime_tagged(VC_TAG, &1u64.to_le_bytes()));
+        assert_eq!(hash_index_with_bit(0, false), U2048::from(2400534839u32));
+        assert_eq!(hash_index_with_bit(1, false), U2048::from(955124969));
+        assert_eq!(hash_index_with_bit(1, false), subroutines::hash_to_prime_tagged(VC_ZERO_TAG, &1u64.to_le_bytes()));
+    }
+
+    #[test]
+    fn test_both_polarities() {
+        let accumulator = U2048::from(2);
+        let indices: Vec<usize> = (0..8).collect();
+        // 0x0F with the bits laid out most significant first(see vc::to_binary)
+        let value = [false, false, false, false, true, true, true, true];
+        let claimed = [true; 8];
+        assert_eq!(commit_with_mode(accumulator, &value, &indices, CommitMode::SetBits), commit(accumulator, &value, &indices));
+
+        let (state, product) = commit_with_mode(accumulator, &value, &indices, CommitMode::BothPolarities);
+        let (pi_i, pi_e) = batch_open_with_mode(accumulator, product, &value, &indices, CommitMode::BothPolarities).unwrap();
+        assert_eq!(batch_verify_with_mode(accumulator, state, &value, &indices, pi_i, pi_e, CommitMode::BothPolarities), true);
+
+        // With only the set bits accumulated, a membership witness for the low bits says nothing about the high bits
+        let (set_state, set_product) = commit(accumulator, &value, &indices);
+        let proof = open_sparse(accumulator, set_product, &[4, 5, 6, 7]).unwrap();
+        assert_eq!(verify_sparse(set_state, &[4, 5, 6, 7], proof), true);
+
+        // Every bit has its own element, so 0x0F cannot be opened or verified as 0xFF
+        assert_eq!(batch_open_with_mode(accumulator, product, &claimed, &indices, CommitMode::BothPolarities), None);
+        assert_eq!(batch_verify_with_mode(accumulator, state, &claimed, &indices, pi_i, pi_e, CommitMode::BothPolarities), false);
+
+        // An index committed with both values cannot be opened
+        let ambiguous = product * hash_index_with_bit(0, true);
+        assert_eq!(batch_open_with_mode(accumulator, ambiguous, &value, &indices, CommitMode::BothPolarities), None);
+    }
+
     #[test]
     fn test_update() {
         let accumulator = U2048::from(2);
         let arr: [bool; 6] = [true, false, true, false, false, true];
         let (state, product) = commit(accumulator, &arr, &[0, 1, 2, 3, 4, 5]);
 
-        let h_0 = subroutines::hash_to_prime(&(0 as usize).to_le_bytes());
-        let h_3 = subroutines::hash_to_prime(&(3 as usize).to_le_bytes());
-        let h_4 = subroutines::hash_to_prime(&(4 as usize).to_le_bytes());
+        let h_0 = hash_index(0);
+        let h_3 = hash_index(3);
+        let h_4 = hash_index(4);
 
         // Missing: checking that inputs are valid
         let new_state = update(state, accumulator, product, &[false, true, true, false], &[2, 3, 4, 5]);
@@ -9,6 +9,11 @@ use codec::{Encode, Decode};
 use accumulator::*;
 pub mod binary;
 pub mod vc;
+pub mod transaction;
+pub mod utxo;
+pub mod proof;
+#[cfg(feature = "std")]
+pub mod test_vectors;
 
 #[cfg_attr(feature = "std", derive(Debug))]
 #[derive(Default, Clone, Encode, Decode, PartialEq, Eq)]
@@ -57,7 +62,8 @@ decl_module! {
         /// NOTE: The key must not exist initially.
         pub fn mint(origin, key: u8, amount: u8) -> Result {
             ensure_signed(origin)?;
-            let (state, product) = vc::commit(State::get(), &[key as usize], &[amount]);
+            let (state, product) = vc::commit(State::get(), &[key as usize], &[amount])
+                .map_err(|_| "Key-value pair is invalid.")?;
             State::put(state);
             Self::deposit_event(Event::TokensMinted(state, product));
             Ok(())
@@ -74,7 +80,7 @@ decl_module! {
 
             // Verify that it is valid
             ensure!(vc::verify_at_key(old_state, State::get(), transaction.sender_key as usize,
-            transaction.sender_balance, pi_i_sender, pi_e_sender), "Opening is invalid.");
+            transaction.sender_balance, pi_i_sender, pi_e_sender).is_ok(), "Opening is invalid.");
 
             // Ensure that the sender isn't spending more than balance
             ensure!(transaction.sender_balance >= transaction.amount, "User is trying to spend more than balance.");
@@ -82,7 +88,7 @@ decl_module! {
             // Verify receiver opening
             let (pi_i_receiver, pi_e_receiver) = transaction.receiver_opening;
             ensure!(vc::verify_at_key(old_state, State::get(), transaction.receiver_key as usize,
-                    transaction.receiver_balance, pi_i_receiver, pi_e_receiver), "Opening is invalid.");
+                    transaction.receiver_balance, pi_i_receiver, pi_e_receiver).is_ok(), "Opening is invalid.");
 
             // Add membership proofs to temporary vector to be processed later
             if let Witness::MemWit(sender_witness) = pi_i_sender {
@@ -111,7 +117,8 @@ decl_module! {
                 .into_iter()
                 .enumerate()
                 .map(|(_, (key, value))| -> U2048 {
-                    let (binary_vec, indices) = vc::convert_key_value(&[key as usize], &[value]);
+                    // Keys are u8 so the index range of a key cannot overflow.
+                    let (binary_vec, indices) = vc::convert_key_value(&[key as usize], &[value]).unwrap();
                     let (p_ones, _) = binary::get_bit_elems(&binary_vec, &indices);
                     return p_ones;
                 })
@@ -200,7 +207,7 @@ mod tests {
             let value: u8 = 10;
             StatelessAccounts::mint(Origin::signed(1), key, value);
 
-            let (binary_vec, indices) = vc::convert_key_value(&[key as usize], &[value]);
+            let (binary_vec, indices) = vc::convert_key_value(&[key as usize], &[value]).unwrap();
             let (p_ones, _) = binary::get_bit_elems(&binary_vec, &indices);
             assert_eq!(StatelessAccounts::get_state(), subroutines::mod_exp(U2048::from(2), p_ones, U2048::from_dec_str(MODULUS).unwrap()));
         });
@@ -224,16 +231,16 @@ mod tests {
             StatelessAccounts::mint(Origin::signed(1), bob_key, bob_balance);
 
             // Derive integer representations for manual testing
-            let alice_elem = vc::get_key_value_elem(alice_key as usize, alice_balance);  // This value would be received from the emitted event.
-            let bob_elem = vc::get_key_value_elem(bob_key as usize, bob_balance);   // This value would be received from the emitted event.
+            let alice_elem = vc::get_key_value_elem(alice_key as usize, alice_balance).unwrap();  // This value would be received from the emitted event.
+            let bob_elem = vc::get_key_value_elem(bob_key as usize, bob_balance).unwrap();   // This value would be received from the emitted event.
             let product = alice_elem * bob_elem;
 
             // Get state after minting
             let state_after_mint = StatelessAccounts::get_state();
 
             // Get openings for each user
-            let (alice_pi_i, alice_pi_e) = vc::open_at_key(generator, product, alice_key as usize, alice_balance);
-            let (bob_pi_i, bob_pi_e) = vc::open_at_key(generator, product, bob_key as usize, bob_balance);
+            let (alice_pi_i, alice_pi_e) = vc::open_at_key(generator, product, alice_key as usize, alice_balance).unwrap();
+            let (bob_pi_i, bob_pi_e) = vc::open_at_key(generator, product, bob_key as usize, bob_balance).unwrap();
 
             // Construct transaction
             let transaction = Transaction {
@@ -261,16 +268,16 @@ mod tests {
             let new_state = StatelessAccounts::get_state();
 
             // Derive integer representations for alice and bob's new key-value stores
-            let new_alice_elem = vc::get_key_value_elem(alice_key as usize, alice_balance-3);  // This value would be received from the emitted event.
-            let new_bob_elem = vc::get_key_value_elem(bob_key as usize, bob_balance+3);  // This value would be received from the emitted event.
+            let new_alice_elem = vc::get_key_value_elem(alice_key as usize, alice_balance-3).unwrap();  // This value would be received from the emitted event.
+            let new_bob_elem = vc::get_key_value_elem(bob_key as usize, bob_balance+3).unwrap();  // This value would be received from the emitted event.
 
             // Create openings with the new balances
-            let (alice_pi_i_new, alice_pi_e_new) = vc::open_at_key(state_after_del, new_alice_elem*new_bob_elem, alice_key as usize, alice_balance-3);
-            let (bob_pi_i_new, bob_pi_e_new) = vc::open_at_key(state_after_del, new_alice_elem*new_bob_elem, bob_key as usize, bob_balance+3);
+            let (alice_pi_i_new, alice_pi_e_new) = vc::open_at_key(state_after_del, new_alice_elem*new_bob_elem, alice_key as usize, alice_balance-3).unwrap();
+            let (bob_pi_i_new, bob_pi_e_new) = vc::open_at_key(state_after_del, new_alice_elem*new_bob_elem, bob_key as usize, bob_balance+3).unwrap();
 
             // Verify that the openings are valid
-            assert_eq!(vc::verify_at_key(state_after_del, new_state, alice_key as usize, alice_balance-3, alice_pi_i_new, alice_pi_e_new), true);
-            assert_eq!(vc::verify_at_key(state_after_del, new_state, bob_key as usize, bob_balance+3, bob_pi_i_new, bob_pi_e_new), true);
+            assert_eq!(vc::verify_at_key(state_after_del, new_state, alice_key as usize, alice_balance-3, alice_pi_i_new, alice_pi_e_new).is_ok(), true);
+            assert_eq!(vc::verify_at_key(state_after_del, new_state, bob_key as usize, bob_balance+3, bob_pi_i_new, bob_pi_e_new).is_ok(), true);
         });
     }
 }
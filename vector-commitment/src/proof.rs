@@ -0,0 +1,203 @@
+/// Inclusion Proof Wire Format.
+/// Bundles the opening of a key-value pair into a single fixed-layout encoding for the network. Every
+/// field has a fixed offset and width so that decoding never depends on a length read from the input.
+///
+/// Layout(PROOF_LEN bytes, the key and value in little endian byte order):
+/// [0]          PROOF_VERSION
+/// [1]          size_of::<T>() of the value type
+/// [2..10]      key as a u64
+/// [10..18]     value as a u64
+/// [18..532]    pi_i
+/// [532..1046]  pi_e
+/// Each witness is WITNESS_LEN bytes: a variant byte(0 = MemWit, 1 = NonMemWit), a 256 byte U2048,
+/// a sign byte(0 = positive, 1 = negative) and a second 256 byte U2048. A MemWit stores its witness
+/// in the first U2048 and leaves the sign byte and the second U2048 zeroed. Each U2048 is big endian,
+/// like in the SCALE encoding of Witness and vc::Commitment.
+
+use accumulator::*;
+use core::convert::TryFrom;
+use rstd::prelude::Vec;
+use crate::vc::{self, ValueType, VerifyError};
+
+/// The version byte of the current layout. Version 1 stored the U2048 values in little endian.
+pub const PROOF_VERSION: u8 = 2;
+
+/// The length of an encoded witness.
+pub const WITNESS_LEN: usize = 1 + 256 + 1 + 256;
+
+/// The length of an encoded proof.
+pub const PROOF_LEN: usize = 18 + 2 * WITNESS_LEN;
+
+/// Reasons an encoded proof can be rejected.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DecodeError {
+    /// The input is not exactly PROOF_LEN bytes(Ex: it was truncated).
+    BadLength,
+    /// The version byte is not PROOF_VERSION.
+    UnsupportedVersion,
+    /// The value was encoded for a different value type.
+    WidthMismatch,
+    /// A witness has an unknown variant, a sign byte other than 0 or 1, or non-zero padding.
+    InvalidWitness,
+    /// The key or value does not fit in its type.
+    OutOfRange,
+}
+
+/// The opening(pi_i, pi_e) of a value at a key.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Proof<T: ValueType> {
+    pub key: usize,
+    pub value: T,
+    pub pi_i: Witness,
+    pub pi_e: Witness,
+}
+
+impl<T: ValueType> Proof<T> {
+    pub fn new(key: usize, value: T, pi_i: Witness, pi_e: Witness) -> Self {
+        return Proof { key, value, pi_i, pi_e };
+    }
+
+    /// Equivalent to vc::verify_at_key.
+    pub fn verify(&self, old_state: U2048, accumulator: U2048) -> Result<(), VerifyError> {
+        return vc::verify_at_key(old_state, accumulator, self.key, self.value, self.pi_i, self.pi_e);
+    }
+
+    /// Encodes the proof in the layout described above. The output is always PROOF_LEN bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::with_capacity(PROOF_LEN);
+        bytes.push(PROOF_VERSION);
+        bytes.push(core::mem::size_of::<T>() as u8);
+        bytes.extend_from_slice(&(self.key as u64).to_le_bytes());
+        bytes.extend_from_slice(&self.value.into().to_le_bytes());
+        write_witness(&mut bytes, self.pi_i);
+        write_witness(&mut bytes, self.pi_e);
+        return bytes;
+    }
+
+    /// Decodes a proof produced by to_bytes. Returns an error rather than panicking on malformed input.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        if bytes.len() != PROOF_LEN {
+            return Err(DecodeError::BadLength);
+        }
+        if bytes[0] != PROOF_VERSION {
+            return Err(DecodeError::UnsupportedVersion);
+        }
+        if bytes[1] as usize != core::mem::size_of::<T>() {
+            return Err(DecodeError::WidthMismatch);
+        }
+
+        let key = usize::try_from(read_u64(&bytes[2..10])).map_err(|_| DecodeError::OutOfRange)?;
+        let value = read_u64(&bytes[10..18]);
+        let bits = core::mem::size_of::<T>() * 8;
+        if bits < 64 && value >> bits != 0 {
+            return Err(DecodeError::OutOfRange);
+        }
+
+        let pi_i = read_witness(&bytes[18..18 + WITNESS_LEN])?;
+        let pi_e = read_witness(&bytes[18 + WITNESS_LEN..])?;
+        return Ok(Proof::new(key, T::from_le_byte_slice(&value.to_le_bytes()), pi_i, pi_e));
+    }
+}
+
+/// Appends the WITNESS_LEN byte encoding of a witness.
+fn write_witness(bytes: &mut Vec<u8>, witness: Witness) {
+    let (variant, first, sign, second) = match witness {
+        Witness::MemWit(mem_wit) => (0, mem_wit, false, U2048::from(0)),
+        Witness::NonMemWit((a, sign_a, b)) => (1, a, sign_a, b),
+    };
+    let mut buf: [u8; 256] = [0; 256];
+    bytes.push(variant);
+    first.to_big_endian(&mut buf);
+    bytes.extend_from_slice(&buf);
+    bytes.push(sign as u8);
+    second.to_big_endian(&mut buf);
+    bytes.extend_from_slice(&buf);
+}
+
+/// Decodes a witness from exactly WITNESS_LEN bytes.
+fn read_witness(bytes: &[u8]) -> Result<Witness, DecodeError> {
+    let first = U2048::from_big_endian(&bytes[1..257]);
+    let sign = bytes[257];
+    let second = U2048::from_big_endian(&bytes[258..]);
+    if sign > 1 {
+        return Err(DecodeError::InvalidWitness);
+    }
+    match bytes[0] {
+        0 if sign == 0 && second == U2048::from(0) => {
+            return Ok(Witness::MemWit(first));
+        },
+        1 => {
+            return Ok(Witness::NonMemWit((first, sign == 1, second)));
+        },
+        _ => {
+            return Err(DecodeError::InvalidWitness);
+        },
+    }
+}
+
+/// Decodes a little endian u64 from exactly 8 bytes.
+fn read_u64(bytes: &[u8]) -> u64 {
+    let mut buf: [u8; 8] = [0; 8];
+    buf.copy_from_slice(bytes);
+    return u64::from_le_bytes(buf);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::Encode;
+
+    #[test]
+    fn test_round_trip() {
+        let accumulator = U2048::from(2);
+        let (state, product) = vc::commit(accumulator, &[0, 1], &[5u16, 700]).unwrap();
+        let (pi_i, pi_e) = vc::open_at_key(accumulator, product, 1, 700u16).unwrap();
+
+        let proof = Proof::new(1, 700u16, pi_i, pi_e);
+        let bytes = proof.to_bytes();
+        assert_eq!(bytes.len(), PROOF_LEN);
+        assert_eq!(&bytes[..2], &[PROOF_VERSION, 2]);
+
+        let decoded = Proof::<u16>::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, proof);
+        assert_eq!(decoded.verify(accumulator, state), Ok(()));
+        assert_eq!(decoded.to_bytes(), bytes);
+
+        // The witnesses are laid out like their SCALE encoding
+        assert_eq!(&bytes[18..18 + 257], &pi_i.encode()[..]);
+        assert_eq!(&bytes[18 + WITNESS_LEN..], &pi_e.encode()[..]);
+
+        let max = Proof::new(usize::max_value(), u64::max_value(), Witness::NonMemWit((U2048::max_value(), true, U2048::max_value())), pi_i);
+        assert_eq!(Proof::<u64>::from_bytes(&max.to_bytes()), Ok(max));
+    }
+
+    #[test]
+    fn test_malformed() {
+        let proof = Proof::new(1, 7u8, Witness::MemWit(U2048::from(12)), Witness::NonMemWit((U2048::from(3), true, U2048::from(5))));
+        let bytes = proof.to_bytes();
+
+        for len in [0, 1, 18, PROOF_LEN - WITNESS_LEN, PROOF_LEN - 1].iter() {
+            assert_eq!(Proof::<u8>::from_bytes(&bytes[..*len]), Err(DecodeError::BadLength));
+        }
+        let mut long = bytes.clone();
+        long.push(0);
+        assert_eq!(Proof::<u8>::from_bytes(&long), Err(DecodeError::BadLength));
+        assert_eq!(Proof::<u16>::from_bytes(&bytes), Err(DecodeError::WidthMismatch));
+
+        let mut tampered = bytes.clone();
+        tampered[0] = PROOF_VERSION + 1;
+        assert_eq!(Proof::<u8>::from_bytes(&tampered), Err(DecodeError::UnsupportedVersion));
+
+        // The value does not fit in a u8
+        let mut tampered = bytes.clone();
+        tampered[11] = 1;
+        assert_eq!(Proof::<u8>::from_bytes(&tampered), Err(DecodeError::OutOfRange));
+
+        // Unknown variant, bad sign byte and non-zero MemWit padding
+        for &(offset, byte) in [(18, 2), (18 + 257, 1), (18 + 300, 1), (18 + WITNESS_LEN + 257, 2)].iter() {
+            let mut tampered = bytes.clone();
+            tampered[offset] = byte;
+            assert_eq!(Proof::<u8>::from_bytes(&tampered), Err(DecodeError::InvalidWitness));
+        }
+    }
+}
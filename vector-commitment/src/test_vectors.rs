@@ -0,0 +1,113 @@
+/// Deterministic Test Vectors.
+/// Generates (keys, values, expected product, expected accumulator) tuples over the vc::commit path
+/// so that other implementations of the protocol can check that they produce the same commitments.
+/// The vectors are derived from SplitMix64 rather than a library PRNG so that ports only need a few
+/// lines of code to reproduce them. The product is included because with the toy MODULUS a port
+/// that derives the wrong primes would still match the accumulator about 1 time in 12.
+///
+/// Derivation of the elements(see binary::hash_index):
+/// - The u8 value of key k occupies the bit indices 8k..8k+8, most significant bit first.
+/// - Only the set bits are accumulated. The element of bit index i is hash_to_prime of the bytes
+///   u32_le(len(VC_TAG)) || VC_TAG || u64_le(i), where VC_TAG is the 29 ASCII bytes
+///   "stateless-blockchain/vc/index". The index is always 8 bytes regardless of the platform.
+/// - hash_to_prime takes the blake2b-256 digest of its input, reads it as a little endian integer and
+///   reduces it mod 2^32 - 1(LAMBDA). Until the result passes Miller-Rabin with the bases 2, 7 and
+///   61, the previous digest is hashed again.
+/// - The product is the product of the elements and the accumulator is 2^product mod MODULUS.
+
+use accumulator::*;
+use crate::vc;
+
+/// The number of vectors produced by generate.
+pub const VECTOR_COUNT: usize = 8;
+
+/// Keys are drawn without replacement from 0..KEY_SPACE.
+pub const KEY_SPACE: usize = 16;
+
+/// The largest number of key-value pairs in a single vector.
+pub const MAX_PAIRS: usize = 4;
+
+/// Advances a SplitMix64 state and returns the next output.
+fn next(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    return z ^ (z >> 31);
+}
+
+/// Generates VECTOR_COUNT reproducible commitments for "seed". Each vector commits 1 to MAX_PAIRS
+/// distinct keys with u8 values to an accumulator initialized at 2.
+/// For each vector, the pair count is 1 + next % MAX_PAIRS, the keys are chosen by a partial
+/// Fisher-Yates shuffle of 0..KEY_SPACE(swapping position i with i + next % (KEY_SPACE - i)), and
+/// each value is the low byte of next.
+pub fn generate(seed: u64) -> Vec<(Vec<usize>, Vec<u8>, U2048, U2048)> {
+    let mut state = seed;
+    let mut vectors = Vec::with_capacity(VECTOR_COUNT);
+    for _ in 0..VECTOR_COUNT {
+        let len = 1 + (next(&mut state) % MAX_PAIRS as u64) as usize;
+        let mut pool: Vec<usize> = (0..KEY_SPACE).collect();
+        for i in 0..len {
+            let j = i + (next(&mut state) % (KEY_SPACE - i) as u64) as usize;
+            pool.swap(i, j);
+        }
+        let keys = pool[..len].to_vec();
+        let values: Vec<u8> = (0..len).map(|_| next(&mut state) as u8).collect();
+        let (accumulator, product) = vc::commit(U2048::from(2), &keys, &values).unwrap();
+        vectors.push((keys, values, product, accumulator));
+    }
+    return vectors;
+}
+
+/// Serializes test vectors with one vector per line in the form "keys/values/product/accumulator".
+/// Keys are comma separated lowercase hex, values are comma separated two digit lowercase hex and the
+/// product and the accumulator are big endian lowercase hex without leading zeros.
+pub fn to_hex(vectors: &[(Vec<usize>, Vec<u8>, U2048, U2048)]) -> String {
+    let mut lines: Vec<String> = Vec::with_capacity(vectors.len());
+    for (keys, values, product, accumulator) in vectors.iter() {
+        let keys: Vec<String> = keys.iter().map(|key| format!("{:x}", key)).collect();
+        let values: Vec<String> = values.iter().map(|value| format!("{:02x}", value)).collect();
+        lines.push(format!("{}/{}/{}/{}", keys.join(","), values.join(","), to_hex_u2048(*product), to_hex_u2048(*accumulator)));
+    }
+    return lines.join("\n");
+}
+
+/// Encodes a U2048 as big endian lowercase hex without leading zeros.
+fn to_hex_u2048(elem: U2048) -> String {
+    let mut bytes: [u8; 256] = [0; 256];
+    elem.to_big_endian(&mut bytes);
+    let digits: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    let trimmed = digits.trim_start_matches('0');
+    if trimmed.is_empty() {
+        return "0".to_string();
+    }
+    return trimmed.to_string();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate() {
+        let vectors = generate(42);
+        assert_eq!(vectors.len(), VECTOR_COUNT);
+        assert_eq!(generate(42), vectors);
+        assert_eq!(to_hex(&generate(42)), to_hex(&vectors));
+        assert_ne!(generate(43), vectors);
+        assert_eq!(to_hex(&vectors).lines().next(), Some("3,4/94,f2/1aaa690b3c5a9352867d5833e1de77bd01cd64d0346bbe4e9e0ab3818eca3/7"));
+
+        for (keys, values, product, accumulator) in vectors.iter() {
+            assert_eq!(keys.len(), values.len());
+            assert_eq!(keys.len() >= 1 && keys.len() <= MAX_PAIRS, true);
+            assert_eq!(vc::commit(U2048::from(2), keys, values).unwrap(), (*accumulator, *product));
+            assert_eq!(vc::product_of(keys, values), Ok(*product));
+        }
+    }
+
+    #[test]
+    fn test_to_hex() {
+        let vectors = vec![(vec![0, 11], vec![5, 255], U2048::from(0x1f4), U2048::from(12)), (vec![3], vec![0], U2048::from(1), U2048::from(0))];
+        assert_eq!(to_hex(&vectors), "0,b/05,ff/1f4/c\n3/00/1/0");
+    }
+}
@@ -0,0 +1,281 @@
+/// Transactions.
+/// A minimal state-transition function over a vector commitment. A transaction changes the value
+/// committed at a single key and carries the opening of the old key-value pair(Ex: the pi_i and pi_e
+/// returned by vc::open_at_key).
+
+use accumulator::*;
+use rstd::prelude::Vec;
+use crate::binary;
+use crate::vc::{self, ValueType, VcError};
+
+/// Changes the value committed at "key" from "old_value" to "new_value". "witness" is the membership
+/// witness of the set bits of "old_value"(pi_i) and "exclusion" is the non-membership witness of its
+/// unset bits(pi_e).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Transaction<T: ValueType> {
+    pub key: usize,
+    pub old_value: T,
+    pub new_value: T,
+    pub witness: Witness,
+    pub exclusion: Witness,
+}
+
+/// Reasons a batch of transactions(or a utxo::Transaction) can be rejected.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TxError {
+    /// Two transactions of the batch change the same key(Ex: a double-spend) or a key appears twice
+    /// among the inputs or the outputs of a utxo::Transaction.
+    ConflictingKeys,
+    /// The witness of a transaction is not a membership witness of its old key-value pair relative to
+    /// the current state, or the opening of a utxo::Transaction input does not verify.
+    InvalidWitness,
+    /// The inputs and outputs of a transaction do not carry the same total value.
+    Unbalanced,
+    /// The total value of the inputs or the outputs of a utxo::Transaction does not fit in the value
+    /// type.
+    Overflow,
+    /// The key-value pairs of a transaction cannot be mapped to accumulator elements.
+    Commitment(VcError),
+}
+
+impl From<VcError> for TxError {
+    fn from(err: VcError) -> Self {
+        return TxError::Commitment(err);
+    }
+}
+
+/// Applies a batch of transactions to "state" and returns the new state of the accumulator. The
+/// opening of every old key-value pair is verified in full(see vc::verify_at_key) and at most one
+/// transaction may change a given key. Verifying the unset bits rejects a transaction that claims a
+/// smaller old value than the committed one, which would otherwise leave the remaining bits in the
+/// new state. The membership witnesses are then aggregated with the Shamir trick into a single
+/// witness for the product of the old key-value pairs, which serves as the "old_state" of
+/// vc::batch_update.
+/// NOTE: "old_state" is required because the non-membership witnesses are relative to it.
+pub fn apply<T: ValueType>(old_state: U2048, state: U2048, txs: &[Transaction<T>]) -> Result<U2048, TxError> {
+    let mut keys: Vec<usize> = txs.iter().map(|tx| tx.key).collect();
+    keys.sort();
+    if keys.windows(2).any(|pair| pair[0] == pair[1]) {
+        return Err(TxError::ConflictingKeys);
+    }
+    if txs.is_empty() {
+        return Ok(state);
+    }
+
+    let mut spent: Vec<(Witness, U2048)> = Vec::with_capacity(txs.len());
+    for tx in txs.iter() {
+        let elem = vc::get_key_value_elem(tx.key, tx.old_value)?;
+        if vc::verify_at_key(old_state, state, tx.key, tx.old_value, tx.witness, tx.exclusion).is_err() {
+            return Err(TxError::InvalidWitness);
+        }
+        spent.push((tx.witness, elem));
+    }
+
+    let agg_elems: Vec<U2048> = spent.iter().map(|(_, elem)| *elem).collect();
+    let agg = subroutines::prime_product(&agg_elems);
+    let agg_witness = match witnesses::aggregate_witnesses(&spent) {
+        Some(Witness::MemWit(witness)) => witness,
+        _ => return Err(TxError::InvalidWitness),
+    };
+
+    let updates: Vec<(usize, T, T)> = txs.iter().map(|tx| (tx.key, tx.old_value, tx.new_value)).collect();
+    return vc::batch_update(agg_witness, agg, &updates).map_err(TxError::from);
+}
+
+/// Reasons a block can be rejected by verify_block.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BlockError {
+    /// Two transactions of the block change the same key(Ex: a double-spend).
+    ConflictingKeys,
+    /// The transaction at this index has a key out of range or an invalid witness.
+    Transaction(usize),
+    /// Every witness verifies on its own but the aggregated witness does not.
+    Aggregate,
+}
+
+/// Equivalent to apply but verifies the membership witnesses of a block with a single aggregated check
+/// instead of one check per transaction. The witnesses are folded with the Shamir trick and the
+/// aggregate is checked once against "state". Only if that check fails are the witnesses checked
+/// individually to find the offending transaction. The non-membership witnesses(exclusion) are
+/// relative to "old_state" and are checked per transaction. Like apply, non-canonical witnesses(see
+/// Witness::validate) are rejected.
+pub fn verify_block<T: ValueType>(old_state: U2048, state: U2048, txs: &[Transaction<T>]) -> Result<U2048, BlockError> {
+    let modulus = U2048::from_dec_str(MODULUS).unwrap();
+    let keys: Vec<usize> = txs.iter().map(|tx| tx.key).collect();
+    vc::validate_indices(&keys).map_err(|_| BlockError::ConflictingKeys)?;
+
+    let mut spent: Vec<(Witness, U2048)> = Vec::with_capacity(txs.len());
+    for (i, tx) in txs.iter().enumerate() {
+        if !tx.witness.validate(modulus) {
+            return Err(BlockError::Transaction(i));
+        }
+        let (binary_vec, indices) = vc::convert_key_value(&[tx.key], &[tx.old_value]).map_err(|_| BlockError::Transaction(i))?;
+        let (p_ones, p_zeros) = binary::get_bit_elems(&binary_vec, &indices);
+        match tx.exclusion {
+            Witness::NonMemWit(non_mem_wit) if tx.exclusion.validate(modulus)
+                && witnesses::verify_non_mem_wit(old_state, state, non_mem_wit, p_zeros) => {},
+            _ => return Err(BlockError::Transaction(i)),
+        }
+        spent.push((tx.witness, p_ones));
+    }
+    if txs.is_empty() {
+        return Ok(state);
+    }
+
+    let agg_elems: Vec<U2048> = spent.iter().map(|(_, elem)| *elem).collect();
+    let agg = subroutines::prime_product(&agg_elems);
+    match witnesses::aggregate_witnesses(&spent) {
+        Some(Witness::MemWit(agg_witness)) if witnesses::verify_mem_wit(state, agg_witness, agg) => {
+            // The keys are distinct and in range so the update cannot fail
+            let updates: Vec<(usize, T, T)> = txs.iter().map(|tx| (tx.key, tx.old_value, tx.new_value)).collect();
+            return Ok(vc::batch_update(agg_witness, agg, &updates).unwrap());
+        },
+        _ => {
+            let offending = spent.iter().position(|(witness, elem)| match witness {
+                Witness::MemWit(witness) => !witnesses::verify_mem_wit(state, *witness, *elem),
+                Witness::NonMemWit(_) => true,
+            });
+            return Err(offending.map(BlockError::Transaction).unwrap_or(BlockError::Aggregate));
+        },
+    }
+}
+
+/// Spends the key-value pair committed at "key" and returns the new state of the accumulator along
+/// with the new aggregated product. "agg_product" is the product of the elements accumulated onto
+/// "old_state"(Ex: the product returned by vc::commit), so the new state is old_state raised to the
+/// product without the spent pair. Returns NotCommitted if the key-value pair is not contained in
+/// "agg_product"(Ex: the key was already spent).
+/// NOTE: The elements are primes, so the product is divided exactly in the integers and no modular
+/// inverse is needed. A value without set bits has no elements and is never contained.
+pub fn spend<T: ValueType>(old_state: U2048, agg_product: U2048, key: usize, value: T) -> Result<(U2048, U2048), TxError> {
+    let elem = vc::get_key_value_elem(key, value)?;
+    if elem == U2048::from(1) || agg_product % elem != U2048::from(0) {
+        return Err(TxError::Commitment(VcError::NotCommitted));
+    }
+    let product = agg_product / elem;
+    return Ok((subroutines::mod_exp(old_state, product, U2048::from_dec_str(MODULUS).unwrap()), product));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let accumulator = U2048::from(2);
+        let (state, product) = vc::commit(accumulator, &[0, 1, 2], &[4u8, 7, 9]).unwrap();
+        let (pi_i_0, pi_e_0) = vc::open_at_key(accumulator, product, 0, 4u8).unwrap();
+        let (pi_i_1, pi_e_1) = vc::open_at_key(accumulator, product, 1, 7u8).unwrap();
+
+        let txs = vec![
+            Transaction { key: 0, old_value: 4u8, new_value: 5, witness: pi_i_0, exclusion: pi_e_0 },
+            Transaction { key: 1, old_value: 7u8, new_value: 3, witness: pi_i_1, exclusion: pi_e_1 },
+        ];
+        let (expected, _) = vc::commit(accumulator, &[0, 1, 2], &[5u8, 3, 9]).unwrap();
+        assert_eq!(apply(accumulator, state, &txs), Ok(expected));
+        assert_eq!(apply(accumulator, state, &txs[..0]), Ok(state));
+    }
+
+    #[test]
+    fn test_verify_block() {
+        let accumulator = U2048::from(2);
+        let keys: Vec<usize> = (0..10).collect();
+        let values: Vec<u8> = (0..10).map(|i| (i % 3) + 1).collect();
+        let (state, product) = vc::commit(accumulator, &keys, &values).unwrap();
+
+        let mut txs: Vec<Transaction<u8>> = keys.iter()
+            .map(|&key| {
+                let (pi_i, pi_e) = vc::open_at_key(accumulator, product, key, values[key]).unwrap();
+                Transaction { key, old_value: values[key], new_value: values[key] + 1, witness: pi_i, exclusion: pi_e }
+            })
+            .collect();
+        let new_values: Vec<u8> = values.iter().map(|value| value + 1).collect();
+        let (expected, _) = vc::commit(accumulator, &keys, &new_values).unwrap();
+        assert_eq!(verify_block(accumulator, state, &txs), Ok(expected));
+        assert_eq!(apply(accumulator, state, &txs), Ok(expected));
+
+        // Transaction 6 carries the witness of transaction 3
+        txs[6].witness = txs[3].witness;
+        assert_eq!(verify_block(accumulator, state, &txs), Err(BlockError::Transaction(6)));
+        txs[8].witness = txs[0].witness;
+        assert_eq!(verify_block(accumulator, state, &txs), Err(BlockError::Transaction(6)));
+
+        // Non-canonical membership witness, rejected by apply as well
+        let modulus = U2048::from_dec_str(MODULUS).unwrap();
+        let mut shifted = txs[..6].to_vec();
+        if let Witness::MemWit(mem_wit) = shifted[4].witness {
+            shifted[4].witness = Witness::MemWit(mem_wit + modulus);
+        }
+        assert_eq!(verify_block(accumulator, state, &shifted), Err(BlockError::Transaction(4)));
+        assert_eq!(apply(accumulator, state, &shifted), Err(TxError::InvalidWitness));
+
+        txs[2].key = 1;
+        assert_eq!(verify_block(accumulator, state, &txs), Err(BlockError::ConflictingKeys));
+        assert_eq!(verify_block::<u8>(accumulator, state, &[]), Ok(state));
+    }
+
+    #[test]
+    fn test_spend() {
+        let accumulator = U2048::from(2);
+        let (_, product) = vc::commit(accumulator, &[0, 1], &[5u8, 7]).unwrap();
+        let (state, new_product) = spend(accumulator, product, 1, 7u8).unwrap();
+        assert_eq!((state, new_product), vc::commit(accumulator, &[0], &[5u8]).unwrap());
+
+        // Key 0 still verifies against the new product
+        let (pi_i, pi_e) = vc::open_at_key(accumulator, new_product, 0, 5u8).unwrap();
+        assert_eq!(vc::verify_at_key(accumulator, state, 0, 5u8, pi_i, pi_e), Ok(()));
+
+        assert_eq!(spend(accumulator, new_product, 1, 7u8), Err(TxError::Commitment(VcError::NotCommitted)));
+        assert_eq!(spend(accumulator, new_product, 2, 5u8), Err(TxError::Commitment(VcError::NotCommitted)));
+        assert_eq!(spend(accumulator, new_product, 0, 0u8), Err(TxError::Commitment(VcError::NotCommitted)));
+    }
+
+    #[test]
+    fn test_apply_double_spend() {
+        let accumulator = U2048::from(2);
+        let (state, product) = vc::commit(accumulator, &[0, 1, 2], &[4u8, 7, 9]).unwrap();
+        let (pi_i, pi_e) = vc::open_at_key(accumulator, product, 0, 4u8).unwrap();
+
+        let txs = vec![
+            Transaction { key: 0, old_value: 4u8, new_value: 5, witness: pi_i, exclusion: pi_e },
+            Transaction { key: 0, old_value: 4u8, new_value: 6, witness: pi_i, exclusion: pi_e },
+        ];
+        assert_eq!(apply(accumulator, state, &txs), Err(TxError::ConflictingKeys));
+    }
+
+    #[test]
+    fn test_apply_invalid_witness() {
+        let accumulator = U2048::from(2);
+        let (state, product) = vc::commit(accumulator, &[0, 1, 2], &[4u8, 7, 9]).unwrap();
+        let (pi_1, pi_e) = vc::open_at_key(accumulator, product, 1, 7u8).unwrap();
+
+        // The witness of key 1 does not open key 0
+        let tx = Transaction { key: 0, old_value: 4u8, new_value: 5, witness: pi_1, exclusion: pi_e };
+        assert_eq!(apply(accumulator, state, &[tx]), Err(TxError::InvalidWitness));
+
+        // A non-membership witness is never accepted as the membership witness
+        let tx = Transaction { key: 1, old_value: 7u8, new_value: 5, witness: pi_e, exclusion: pi_e };
+        assert_eq!(apply(accumulator, state, &[tx]), Err(TxError::InvalidWitness));
+
+        let tx = Transaction { key: vc::max_key::<u8>() + 1, old_value: 7u8, new_value: 5, witness: pi_1, exclusion: pi_e };
+        assert_eq!(apply(accumulator, state, &[tx]), Err(TxError::Commitment(VcError::KeyOutOfRange)));
+    }
+
+    #[test]
+    fn test_apply_smaller_old_value() {
+        let accumulator = U2048::from(2);
+        let (state, product) = vc::commit(accumulator, &[0, 1, 2], &[4u8, 7, 9]).unwrap();
+        let (_, pi_e) = vc::open_at_key(accumulator, product, 2, 9u8).unwrap();
+
+        // 1 = 0b0001 is a subset of 9 = 0b1001, so its membership witness is valid on its own
+        let elem = vc::get_key_value_elem(2, 1u8).unwrap();
+        let pi_i = Witness::MemWit(witnesses::mem_wit_create(accumulator, product, elem).unwrap());
+        assert_eq!(vc::verify_membership(state, 2, 1u8, pi_i), true);
+
+        // Bit 3 is still accumulated, so the unset bits of the claimed value do not verify
+        let tx = Transaction { key: 2, old_value: 1u8, new_value: 0, witness: pi_i, exclusion: pi_e };
+        assert_eq!(apply(accumulator, state, &[tx]), Err(TxError::InvalidWitness));
+        assert_eq!(verify_block(accumulator, state, &[tx]), Err(BlockError::Transaction(0)));
+        assert_eq!(vc::open_at_key(accumulator, product, 2, 1u8), Err(VcError::ValuePresent));
+    }
+}
@@ -0,0 +1,278 @@
+/// UTXO Transactions.
+/// Transfers value between key-value pairs of a vector commitment. Unlike transaction::apply, a
+/// transaction consumes its inputs entirely and creates new key-value pairs for its outputs. As with
+/// transaction::apply, the openings of the inputs must prove the complete value(both pi_i and pi_e of
+/// vc::open_at_key).
+
+use accumulator::*;
+use rstd::prelude::Vec;
+use crate::vc::{self, ValueType, VcError};
+use crate::transaction::TxError;
+
+/// Spends the key-value pairs of "inputs" and creates the key-value pairs of "outputs". The opening
+/// of the i-th input is the i-th element of "witnesses".
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Transaction<T: ValueType> {
+    pub inputs: Vec<(usize, T)>,
+    pub outputs: Vec<(usize, T)>,
+    pub witnesses: Vec<(Witness, Witness)>,
+}
+
+/// Applies a transaction to "state" and returns the new state of the accumulator. Every input must
+/// verify at its key relative to "old_state"(see vc::verify_at_key), the values of the inputs and
+/// outputs must sum to the same total, and no key may appear twice among the inputs or the outputs.
+/// The totals are computed in a u128 and must fit in T, otherwise Overflow is returned.
+/// The inputs are deleted with a single membership witness aggregated with the Shamir trick and then
+/// the outputs are added. An input that was already spent(Ex: a double-spend) fails to verify.
+/// NOTE: "old_state" is required because the non-membership witnesses are relative to it. The output
+/// keys are not checked against the commitment, so an output must not reuse a key that is still held
+/// by a key-value pair other than the inputs.
+pub fn apply_transaction<T: ValueType>(old_state: U2048, state: U2048, tx: &Transaction<T>) -> Result<U2048, TxError> {
+    if tx.inputs.len() != tx.witnesses.len() {
+        return Err(TxError::Commitment(VcError::LengthMismatch));
+    }
+    let (in_keys, _) = split_pairs(&tx.inputs);
+    let (out_keys, out_values) = split_pairs(&tx.outputs);
+    if vc::validate_indices(&in_keys).is_err() || vc::validate_indices(&out_keys).is_err() {
+        return Err(TxError::ConflictingKeys);
+    }
+    let (total_in, total_out) = (sum_values(&tx.inputs), sum_values(&tx.outputs));
+    let max_total = u128::max_value() >> (128 - vc::bits_per_value::<T>());
+    if total_in > max_total || total_out > max_total {
+        return Err(TxError::Overflow);
+    }
+    if total_in != total_out {
+        return Err(TxError::Unbalanced);
+    }
+
+    let mut spent: Vec<(Witness, U2048)> = Vec::with_capacity(tx.inputs.len());
+    for (&(key, value), &(pi_i, pi_e)) in tx.inputs.iter().zip(tx.witnesses.iter()) {
+        if vc::verify_at_key(old_state, state, key, value, pi_i, pi_e).is_err() {
+            return Err(TxError::InvalidWitness);
+        }
+        spent.push((pi_i, vc::get_key_value_elem(key, value)?));
+    }
+
+    // Delete the inputs
+    let mut new_state = state;
+    if !spent.is_empty() {
+        new_state = match witnesses::aggregate_witnesses(&spent) {
+            Some(Witness::MemWit(witness)) => witness,
+            _ => return Err(TxError::InvalidWitness),
+        };
+    }
+
+    // Add the outputs
+    return Ok(add(new_state, vc::product_of(&out_keys, &out_values)?));
+}
+
+/// A single verification object for a block of transactions. "witness" is the membership witness of
+/// every input aggregated with the Shamir trick, which is also the state after the inputs are deleted.
+/// "delete_proof" is a proof of exponentiation that witness^inputs = old_state and "add_proof" is a
+/// proof of exponentiation that witness^outputs = new_state.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BlockProof<T: ValueType> {
+    pub inputs: Vec<(usize, T)>,
+    pub outputs: Vec<(usize, T)>,
+    pub witness: U2048,
+    pub delete_proof: proofs::PoeProof,
+    pub add_proof: proofs::PoeProof,
+}
+
+/// Combines the inclusion proofs(pi_i) of the inputs of every transaction of a block into a single
+/// BlockProof. Returns None if the block has no inputs, if a transaction does not have one opening
+/// per input, or if the witnesses cannot be aggregated(Ex: two transactions spend the same input or
+/// the witnesses are relative to different states).
+/// NOTE: Only the set bits of the inputs are covered by the aggregate. The openings of the inputs
+/// should still be verified in full(see apply_transaction) before a transaction enters a block.
+pub fn aggregate_block_proof<T: ValueType>(txs: &[Transaction<T>]) -> Option<BlockProof<T>> {
+    let mut inputs: Vec<(usize, T)> = Vec::new();
+    let mut outputs: Vec<(usize, T)> = Vec::new();
+    let mut spent: Vec<(Witness, U2048)> = Vec::new();
+    for tx in txs.iter() {
+        if tx.inputs.len() != tx.witnesses.len() {
+            return None;
+        }
+        for (&(key, value), &(pi_i, _)) in tx.inputs.iter().zip(tx.witnesses.iter()) {
+            spent.push((pi_i, vc::get_key_value_elem(key, value).ok()?));
+        }
+        inputs.extend_from_slice(&tx.inputs);
+        outputs.extend_from_slice(&tx.outputs);
+    }
+
+    let witness = match witnesses::aggregate_witnesses(&spent)? {
+        Witness::MemWit(witness) => witness,
+        Witness::NonMemWit(_) => return None,
+    };
+    let (in_keys, in_values) = split_pairs(&inputs);
+    let (out_keys, out_values) = split_pairs(&outputs);
+    let p_inputs = vc::product_of(&in_keys, &in_values).ok()?;
+    let p_outputs = vc::product_of(&out_keys, &out_values).ok()?;
+    let old_state = subroutines::mod_exp(witness, p_inputs, U2048::from_dec_str(MODULUS).unwrap());
+    let new_state = subroutines::mod_exp(witness, p_outputs, U2048::from_dec_str(MODULUS).unwrap());
+    return Some(BlockProof {
+        inputs,
+        outputs,
+        witness,
+        delete_proof: proofs::poe(witness, p_inputs, old_state),
+        add_proof: proofs::poe(witness, p_outputs, new_state),
+    });
+}
+
+/// Verifies that a block transitions the accumulator from "old_state" to "new_state" by deleting the
+/// inputs and adding the outputs of the BlockProof. The products of the inputs and outputs are
+/// recomputed from the listed key-value pairs(see vc::product_of, which also rejects a key listed
+/// twice), so a block fails to verify if the aggregate does not cover every listed input. The total
+/// value of the inputs and outputs must also match.
+pub fn verify_block_proof<T: ValueType>(old_state: U2048, new_state: U2048, proof: &BlockProof<T>) -> bool {
+    if sum_values(&proof.inputs) != sum_values(&proof.outputs) {
+        return false;
+    }
+
+    let (in_keys, in_values) = split_pairs(&proof.inputs);
+    let (out_keys, out_values) = split_pairs(&proof.outputs);
+    match (vc::product_of(&in_keys, &in_values), vc::product_of(&out_keys, &out_values)) {
+        (Ok(p_inputs), Ok(p_outputs)) => {
+            return proofs::verify_poe(proof.witness, p_inputs, old_state, proof.delete_proof)
+                && proofs::verify_poe(proof.witness, p_outputs, new_state, proof.add_proof);
+        },
+        _ => {
+            return false;
+        },
+    }
+}
+
+/// Splits a set of key-value pairs into the keys and the values expected by the vc functions.
+fn split_pairs<T: ValueType>(pairs: &[(usize, T)]) -> (Vec<usize>, Vec<T>) {
+    return pairs.iter().cloned().unzip();
+}
+
+/// Sums the values of a set of key-value pairs. A u128 cannot overflow for fewer than 2^64 pairs.
+fn sum_values<T: ValueType>(pairs: &[(usize, T)]) -> u128 {
+    return pairs.iter().map(|(_, value)| (*value).into() as u128).sum();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_transaction() {
+        let accumulator = U2048::from(2);
+        let (state, product) = vc::commit(accumulator, &[0, 1, 2], &[4u8, 7, 9]).unwrap();
+        let tx = Transaction {
+            inputs: vec![(0, 4u8), (1, 7)],
+            outputs: vec![(0, 5), (3, 6)],
+            witnesses: vec![vc::open_at_key(accumulator, product, 0, 4u8).unwrap(), vc::open_at_key(accumulator, product, 1, 7u8).unwrap()],
+        };
+
+        let new_state = apply_transaction(accumulator, state, &tx).unwrap();
+        let (expected, _) = vc::commit(accumulator, &[2, 0, 3], &[9u8, 5, 6]).unwrap();
+        assert_eq!(new_state, expected);
+
+        // The inputs were spent
+        assert_eq!(apply_transaction(accumulator, new_state, &tx), Err(TxError::InvalidWitness));
+
+        let mut tampered = tx.clone();
+        tampered.inputs[0] = (0, 5);
+        tampered.outputs[0] = (0, 6);
+        assert_eq!(apply_transaction(accumulator, state, &tampered), Err(TxError::InvalidWitness));
+
+        let mut tampered = tx.clone();
+        tampered.witnesses.pop();
+        assert_eq!(apply_transaction(accumulator, state, &tampered), Err(TxError::Commitment(VcError::LengthMismatch)));
+
+        let mut tampered = tx.clone();
+        tampered.outputs = vec![(3, 5), (3, 6)];
+        assert_eq!(apply_transaction(accumulator, state, &tampered), Err(TxError::ConflictingKeys));
+    }
+
+    #[test]
+    fn test_block_proof() {
+        let accumulator = U2048::from(2);
+        let values: Vec<u8> = (0..10).map(|i| (i % 3) + 1).collect();
+        let mut keys: Vec<usize> = (0..10).collect();
+        keys.push(20);
+        let mut committed = values.clone();
+        committed.push(5);
+        let (state, product) = vc::commit(accumulator, &keys, &committed).unwrap();
+
+        // Every transaction moves the value of key i to key 10 + i
+        let txs: Vec<Transaction<u8>> = (0..10)
+            .map(|i| Transaction {
+                inputs: vec![(i, values[i])],
+                outputs: vec![(10 + i, values[i])],
+                witnesses: vec![vc::open_at_key(accumulator, product, i, values[i]).unwrap()],
+            })
+            .collect();
+        let proof = aggregate_block_proof(&txs).unwrap();
+        assert_eq!(proof.inputs.len(), 10);
+
+        let mut keys: Vec<usize> = (10..20).collect();
+        keys.push(20);
+        let (new_state, _) = vc::commit(accumulator, &keys, &committed).unwrap();
+        assert_eq!(verify_block_proof(state, new_state, &proof), true);
+        assert_eq!(verify_block_proof(state, state, &proof), false);
+
+        // An input that is not covered by the aggregate
+        let mut uncovered = proof.clone();
+        uncovered.inputs.remove(6);
+        uncovered.outputs.remove(6);
+        assert_eq!(verify_block_proof(state, new_state, &uncovered), false);
+
+        // The same input is spent twice
+        let mut double_spend = txs.clone();
+        double_spend.push(txs[3].clone());
+        assert_eq!(aggregate_block_proof(&double_spend), None);
+        assert_eq!(aggregate_block_proof::<u8>(&[]), None);
+
+        // Two transactions create the same output key
+        let mut colliding = txs.clone();
+        colliding[4].outputs[0].0 = 13;
+        assert_eq!(aggregate_block_proof(&colliding), None);
+        let mut colliding = proof.clone();
+        colliding.outputs[4].0 = 13;
+        assert_eq!(verify_block_proof(state, new_state, &colliding), false);
+    }
+
+    #[test]
+    fn test_apply_transaction_unbalanced() {
+        let accumulator = U2048::from(2);
+        let (state, product) = vc::commit(accumulator, &[0, 1, 2], &[4u8, 7, 9]).unwrap();
+        let mut tx = Transaction {
+            inputs: vec![(0, 4u8), (1, 7)],
+            outputs: vec![(0, 6), (3, 6)],
+            witnesses: vec![vc::open_at_key(accumulator, product, 0, 4u8).unwrap(), vc::open_at_key(accumulator, product, 1, 7u8).unwrap()],
+        };
+        assert_eq!(apply_transaction(accumulator, state, &tx), Err(TxError::Unbalanced));
+
+        // Minting without inputs
+        tx.inputs.clear();
+        tx.witnesses.clear();
+        assert_eq!(apply_transaction(accumulator, state, &tx), Err(TxError::Unbalanced));
+    }
+
+    #[test]
+    fn test_apply_transaction_overflow() {
+        let accumulator = U2048::from(2);
+        let (state, product) = vc::commit(accumulator, &[0, 1], &[200u8, 100]).unwrap();
+        let mut tx = Transaction {
+            inputs: vec![(0, 200u8), (1, 100)],
+            outputs: vec![(2, 44)],
+            witnesses: vec![vc::open_at_key(accumulator, product, 0, 200u8).unwrap(), vc::open_at_key(accumulator, product, 1, 100u8).unwrap()],
+        };
+        // 200 + 100 wraps to 44 in a u8
+        assert_eq!(apply_transaction(accumulator, state, &tx), Err(TxError::Overflow));
+
+        tx.outputs = vec![(2, 150), (3, 150)];
+        assert_eq!(apply_transaction(accumulator, state, &tx), Err(TxError::Overflow));
+
+        tx.inputs = vec![(0, 200u8)];
+        tx.witnesses.pop();
+        tx.outputs = vec![(2, 200), (3, 100)];
+        assert_eq!(apply_transaction(accumulator, state, &tx), Err(TxError::Overflow));
+
+        tx.outputs = vec![(2, 100), (3, 100)];
+        assert_eq!(apply_transaction(accumulator, state, &tx).is_ok(), true);
+    }
+}
@@ -4,121 +4,2692 @@
 use accumulator::*;
 use rstd::prelude::Vec;
 use bit_vec::BitVec;
+use codec::{Encode, Decode};
+#[cfg(feature = "std")]
+use serde::{Serialize, Deserialize};
+#[cfg(feature = "std")]
+use std::collections::HashMap;
 use crate::binary;
+use runtime_io::blake2_256;
 
-type ValueType = u8;
+/// Unsigned integer types that can be committed as values. The bit width of the type determines
+/// how many indices are reserved for each key.
+pub trait ValueType: Copy + Into<u64> {
+    /// Returns the little endian byte representation of the value.
+    fn to_le_byte_vec(self) -> Vec<u8>;
 
-/// Commit to a set of keys and corresponding values.
-pub fn commit(accumulator: U2048, keys: &[usize], values: &[ValueType]) -> (U2048, U2048) {
-    let (binary_vec, indices) = convert_key_value(keys, values);
-    return binary::commit(accumulator, &binary_vec, &indices);
+    /// Reconstructs a value from its little endian byte representation. Missing bytes are treated
+    /// as zero and extra bytes are ignored.
+    fn from_le_byte_slice(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_value_type {
+    ($($t:ty),*) => {
+        $(
+            impl ValueType for $t {
+                fn to_le_byte_vec(self) -> Vec<u8> {
+                    return self.to_le_bytes().to_vec();
+                }
+
+                fn from_le_byte_slice(bytes: &[u8]) -> Self {
+                    let mut buf = [0u8; core::mem::size_of::<$t>()];
+                    let len = core::cmp::min(bytes.len(), buf.len());
+                    buf[..len].copy_from_slice(&bytes[..len]);
+                    return <$t>::from_le_bytes(buf);
+                }
+            }
+        )*
+    };
+}
+
+impl_value_type!(u8, u16, u32, u64);
+
+/// Errors produced when key-value pairs cannot be mapped to bit indices.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VcError {
+    /// The key and value slices have different lengths.
+    LengthMismatch,
+    /// A key maps to indices that exceed the supported index domain(usize).
+    KeyOutOfRange,
+    /// The key has at least one set bit in the commitment.
+    KeyPresent,
+    /// Two keys claim the same index(Ex: duplicate keys).
+    OverlappingIndices,
+    /// The key-value pair is not contained in the aggregated product.
+    NotCommitted,
+    /// The witnesses have the wrong form or cannot be updated with the given changes.
+    BadWitness,
+    /// Every set bit of the value is contained in the commitment.
+    ValuePresent,
+    /// The value does not fit in the requested number of bits.
+    OutOfRange,
+    /// The state of a commitment is not the accumulator raised to its product.
+    StateMismatch,
+    /// The generator is not a unit of the RSA group other than 1 and MODULUS-1.
+    InvalidGenerator,
+}
+
+/// The output of a commitment: the new state of the accumulator and the product of the accumulated
+/// elements. Encodes each value as a fixed-width 256 byte big-endian integer(state first).
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+pub struct Commitment {
+    pub state: U2048,
+    pub product: U2048,
+}
+
+impl Encode for Commitment {
+    fn size_hint(&self) -> usize {
+        return 512;
+    }
+
+    fn encode_to<W: codec::Output>(&self, dest: &mut W) {
+        encode_be(&self.state, dest);
+        encode_be(&self.product, dest);
+    }
+}
+
+impl Decode for Commitment {
+    fn decode<I: codec::Input>(input: &mut I) -> Result<Self, codec::Error> {
+        let state = decode_be(input)?;
+        let product = decode_be(input)?;
+        return Ok(Commitment { state, product });
+    }
+}
+
+impl From<(U2048, U2048)> for Commitment {
+    fn from((state, product): (U2048, U2048)) -> Self {
+        Commitment { state, product }
+    }
+}
+
+/// Collects key-value pairs incrementally(Ex: as transactions arrive) and commits them all at once.
+/// The result of finalize matches a single call to commit on the same pairs in any order.
+pub struct CommitmentBuilder<T: ValueType> {
+    accumulator: U2048,
+    bits: Vec<bool>,
+    indices: Vec<usize>,
+    _value: core::marker::PhantomData<T>,
+}
+
+impl<T: ValueType> CommitmentBuilder<T> {
+    pub fn new(accumulator: U2048) -> Self {
+        return CommitmentBuilder {
+            accumulator,
+            bits: Vec::new(),
+            indices: Vec::new(),
+            _value: core::marker::PhantomData,
+        };
+    }
+
+    /// Adds a key-value pair. Duplicate keys are only detected by finalize.
+    pub fn add(&mut self, key: usize, value: T) -> Result<(), VcError> {
+        let (mut binary_vec, mut indices) = convert_key_value(&[key], &[value])?;
+        self.bits.append(&mut binary_vec);
+        self.indices.append(&mut indices);
+        return Ok(());
+    }
+
+    /// Commits to every added key-value pair.
+    pub fn finalize(self) -> Result<(U2048, U2048), VcError> {
+        validate_indices(&self.indices)?;
+        return Ok(binary::commit(self.accumulator, &self.bits, &self.indices));
+    }
+}
+
+/// Tracks the full key-value map off-chain(Ex: for a full node) along with the current root, so that
+/// each change is applied to the root incrementally instead of recommitting every pair. An absent key
+/// is treated as a key committed with a value of zero.
+#[cfg(feature = "std")]
+pub struct StateTree<T: ValueType> {
+    accumulator: U2048,
+    values: HashMap<usize, T>,
+    root: U2048,
+    product: U2048,
+}
+
+#[cfg(feature = "std")]
+impl<T: ValueType> StateTree<T> {
+    /// Creates an empty tree whose root starts at "accumulator".
+    pub fn new(accumulator: U2048) -> Self {
+        return StateTree {
+            accumulator,
+            values: HashMap::new(),
+            root: accumulator,
+            product: U2048::from(1),
+        };
+    }
+
+    /// Sets the value at a key and returns the new root.
+    pub fn set(&mut self, key: usize, value: T) -> Result<U2048, VcError> {
+        let old_value = self.get(key).unwrap_or(T::from_le_byte_slice(&[]));
+        self.root = update_single(self.root, self.accumulator, self.product, key, old_value, value)?;
+        self.product = self.product / get_key_value_elem(key, old_value)? * get_key_value_elem(key, value)?;
+        self.values.insert(key, value);
+        return Ok(self.root);
+    }
+
+    pub fn get(&self, key: usize) -> Option<T> {
+        return self.values.get(&key).copied();
+    }
+
+    pub fn root(&self) -> U2048 {
+        return self.root;
+    }
+
+    /// Returns the product of the accumulated elements(needed to open the tree at a key).
+    pub fn product(&self) -> U2048 {
+        return self.product;
+    }
+}
+
+/// A non-membership witness in the form produced by witnesses::non_mem_wit_create.
+pub type NonMembershipWitness = (U2048, bool, U2048);
+
+/// A membership witness paired with the element it proves, so that callers do not need to track
+/// the element separately.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode)]
+pub struct MembershipWitness {
+    pub element: U2048,
+    pub proof: Witness,
+}
+
+impl MembershipWitness {
+    pub fn new(element: U2048, proof: Witness) -> Self {
+        return MembershipWitness { element, proof };
+    }
+
+    /// Creates the membership witness for the set bits of a key-value pair. The current state of
+    /// the accumulator must equal old_state^product.
+    pub fn from_key_value<T: ValueType>(old_state: U2048, product: U2048, key: usize, value: T) -> Result<Self, VcError> {
+        let element = get_key_value_elem(key, value)?;
+        let mem_wit = witnesses::mem_wit_create(old_state, product, element).ok_or(VcError::NotCommitted)?;
+        return Ok(MembershipWitness::new(element, Witness::MemWit(mem_wit)));
+    }
+
+    /// Equivalent to witnesses::verify_mem_wit. A non-membership proof never verifies.
+    pub fn verify(&self, accumulator: U2048) -> bool {
+        match self.proof {
+            Witness::MemWit(mem_wit) => {
+                return witnesses::verify_mem_wit(accumulator, mem_wit, self.element);
+            },
+            Witness::NonMemWit(_) => {
+                return false;
+            },
+        }
+    }
+}
+
+/// Verifies openings against nothing but the root of the accumulator, so a light client only needs
+/// to sync the root. The root is exchanged as a 256 byte big endian integer, the same encoding as the
+/// state of a Commitment(see from_bytes).
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct LightVerifier {
+    root: U2048,
+}
+
+impl Encode for LightVerifier {
+    fn size_hint(&self) -> usize {
+        return 256;
+    }
+
+    fn encode_to<W: codec::Output>(&self, dest: &mut W) {
+        encode_be(&self.root, dest);
+    }
+}
+
+impl Decode for LightVerifier {
+    fn decode<I: codec::Input>(input: &mut I) -> Result<Self, codec::Error> {
+        return Ok(LightVerifier::new(decode_be(input)?));
+    }
+}
+
+impl LightVerifier {
+    pub fn new(root: U2048) -> Self {
+        return LightVerifier { root };
+    }
+
+    /// Decodes the root from its 256 byte big endian encoding(Ex: the first 256 bytes of an encoded
+    /// Commitment).
+    pub fn from_bytes(bytes: &[u8; 256]) -> Self {
+        return LightVerifier::new(U2048::from_big_endian(bytes));
+    }
+
+    pub fn root(&self) -> U2048 {
+        return self.root;
+    }
+
+    /// Verifies the opening of a key-value pair against the root. The inclusion proof(pi_i) is
+    /// checked with verify_membership.
+    /// NOTE: The exclusion proof(pi_e) is relative to the state before the commitment, which the
+    /// light client does not track, so only its form is checked. The unset bits of the value are
+    /// therefore not proven(use verify_at_key if the old state is available).
+    pub fn verify_inclusion<T: ValueType>(&self, key: usize, value: T, pi_i: Witness, pi_e: Witness) -> bool {
+        match pi_e {
+            Witness::MemWit(_) => {
+                return false;
+            },
+            Witness::NonMemWit(_) => {
+                return verify_membership(self.root, key, value, pi_i);
+            },
+        }
+    }
+}
+
+/// Reasons an opening can fail to verify.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VerifyError {
+    /// The witnesses have the wrong form(Ex: a non-membership witness as the inclusion proof).
+    BadWitness,
+    /// A key maps to indices that exceed the supported index domain(usize).
+    KeyOutOfRange,
+    /// The key and value slices have different lengths.
+    LengthMismatch,
+    /// Two keys claim the same index(Ex: duplicate keys).
+    OverlappingIndices,
+    /// The set bits of the value are not contained in the accumulator.
+    ValueMismatch,
+    /// The unset bits of the value are not excluded from the accumulator relative to old_state.
+    /// This typically means that old_state and the accumulator do not correspond.
+    AccumulatorMismatch,
+}
+
+impl From<VcError> for VerifyError {
+    fn from(err: VcError) -> Self {
+        match err {
+            VcError::LengthMismatch => VerifyError::LengthMismatch,
+            VcError::KeyOutOfRange => VerifyError::KeyOutOfRange,
+            VcError::KeyPresent => VerifyError::ValueMismatch,
+            VcError::OverlappingIndices => VerifyError::OverlappingIndices,
+            VcError::NotCommitted => VerifyError::ValueMismatch,
+            VcError::BadWitness => VerifyError::BadWitness,
+            VcError::ValuePresent => VerifyError::ValueMismatch,
+            VcError::OutOfRange => VerifyError::ValueMismatch,
+            VcError::StateMismatch => VerifyError::AccumulatorMismatch,
+            VcError::InvalidGenerator => VerifyError::AccumulatorMismatch,
+        }
+    }
+}
+
+/// Commit to a set of keys and corresponding values. Committing no keys leaves the accumulator
+/// unchanged and returns the empty product 1.
+pub fn commit<T: ValueType>(accumulator: U2048, keys: &[usize], values: &[T]) -> Result<(U2048, U2048), VcError> {
+    if keys.is_empty() && values.is_empty() {
+        return Ok((accumulator, U2048::from(1)));
+    }
+    let (binary_vec, indices) = convert_key_value(keys, values)?;
+    return Ok(binary::commit(accumulator, &binary_vec, &indices));
+}
+
+/// Builds the genesis state of a chain from an initial allocation of key-value pairs(Ex: premined
+/// balances). The pairs are committed to the canonical base U2048::from(2), which is also the
+/// initial state of the runtime modules. The returned product lets the holders of the allocation
+/// derive their witnesses with open_at_key(U2048::from(2), product, key, value). Returns
+/// OverlappingIndices if a key is allocated more than once.
+pub fn genesis<T: ValueType>(allocation: &[(usize, T)]) -> Result<(U2048, U2048), VcError> {
+    return genesis_with_generator(U2048::from(2), allocation);
+}
+
+/// Equivalent to genesis but commits the allocation to "generator"(Ex: a generator derived from a
+/// setup) instead of the canonical base U2048::from(2).
+pub fn genesis_with_generator<T: ValueType>(generator: U2048, allocation: &[(usize, T)]) -> Result<(U2048, U2048), VcError> {
+    let keys: Vec<usize> = allocation.iter().map(|(key, _)| *key).collect();
+    let values: Vec<T> = allocation.iter().map(|(_, value)| *value).collect();
+    return commit_with_generator(generator, &keys, &values);
+}
+
+/// Equivalent to commit but first checks that the base is a valid generator(see validate_generator).
+/// Use it for the first commitment to a fresh generator. Later commitments build on a state that is
+/// already in the group and go through commit.
+pub fn commit_with_generator<T: ValueType>(generator: U2048, keys: &[usize], values: &[T]) -> Result<(U2048, U2048), VcError> {
+    validate_generator(generator)?;
+    return commit(generator, keys, values);
+}
+
+/// Checks that "generator" is a unit of the RSA group defined by MODULUS. The elements 1 and
+/// MODULUS-1 have a known order(1 and 2), so witnesses relative to them could be forged and they are
+/// rejected as well.
+pub fn validate_generator(generator: U2048) -> Result<(), VcError> {
+    let modulus = U2048::from_dec_str(MODULUS).unwrap();
+    if generator <= U2048::from(1) || generator >= modulus - U2048::from(1) {
+        return Err(VcError::InvalidGenerator);
+    }
+    if subroutines::bezout(generator, modulus).is_none() {
+        return Err(VcError::InvalidGenerator);
+    }
+    return Ok(());
+}
+
+/// Merges shards that commit disjoint key ranges from the same "base" into a single commitment. Each
+/// shard is given as the (state, product) returned by commit together with the range of keys it
+/// commits. The products of disjoint shards are coprime, so the merged state is the state of the first
+/// shard raised to the products of the others and the merged product is the product of the shards.
+/// Returns OverlappingIndices if the key ranges of two shards overlap or two shards share an element
+/// and StateMismatch if the state of a shard is not base^product.
+/// NOTE: The key ranges are not checked against the products, a shard that commits a key outside of
+/// its range is only rejected if it shares an element with another shard.
+pub fn merge(base: U2048, shards: &[(U2048, U2048, core::ops::Range<usize>)]) -> Result<(U2048, U2048), VcError> {
+    for (i, (_, _, keys)) in shards.iter().enumerate() {
+        if shards[..i].iter().any(|(_, _, other)| keys.start < other.end && other.start < keys.end) {
+            return Err(VcError::OverlappingIndices);
+        }
+    }
+
+    let modulus = U2048::from_dec_str(MODULUS).unwrap();
+    let mut state = base;
+    let mut product = U2048::from(1);
+    for &(shard_state, shard_product, _) in shards.iter() {
+        if subroutines::mod_exp(base, shard_product, modulus) != shard_state {
+            return Err(VcError::StateMismatch);
+        }
+        if subroutines::bezout(product, shard_product).is_none() {
+            return Err(VcError::OverlappingIndices);
+        }
+        state = subroutines::mod_exp(state, shard_product, modulus);
+        product = product * shard_product;
+    }
+    return Ok((state, product));
+}
+
+/// Equivalent to commit but also returns the time spent in each phase(see binary::CommitStats).
+#[cfg(feature = "metrics")]
+pub fn commit_instrumented<T: ValueType>(accumulator: U2048, keys: &[usize], values: &[T]) -> Result<((U2048, U2048), binary::CommitStats), VcError> {
+    let (binary_vec, indices) = convert_key_value(keys, values)?;
+    return Ok(binary::commit_instrumented(accumulator, &binary_vec, &indices));
+}
+
+/// Equivalent to commit in the RSA group defined by an arbitrary "modulus" rather than MODULUS.
+pub fn commit_with_modulus<T: ValueType>(accumulator: U2048, keys: &[usize], values: &[T], modulus: U2048) -> Result<(U2048, U2048), VcError> {
+    let (binary_vec, indices) = convert_key_value(keys, values)?;
+    let (product, _) = binary::get_bit_elems(&binary_vec, &indices);
+    return Ok((subroutines::mod_exp(accumulator, product, modulus), product));
+}
+
+/// Equivalent to open_at_key in the RSA group defined by "modulus".
+pub fn open_at_key_with_modulus<T: ValueType>(old_state: U2048, product: U2048, key: usize, value: T, modulus: U2048) -> Result<(Witness, Witness), VcError> {
+    let (binary_vec, indices) = convert_key_value(&[key], &[value])?;
+    let (p_ones, p_zeros) = binary::get_bit_elems(&binary_vec, &indices);
+    let mem_wit = witnesses::mem_wit_create_with_modulus(old_state, product, p_ones, modulus).ok_or(VcError::NotCommitted)?;
+    if subroutines::bezout(product, p_zeros).is_none() {
+        return Err(VcError::ValuePresent);
+    }
+    let non_mem_wit = witnesses::non_mem_wit_create_with_modulus(old_state, product, p_zeros, modulus);
+    return Ok((Witness::MemWit(mem_wit), Witness::NonMemWit(non_mem_wit)));
+}
+
+/// Equivalent to verify_at_key in the RSA group defined by "modulus".
+pub fn verify_at_key_with_modulus<T: ValueType>(old_state: U2048, accumulator: U2048, key: usize, value: T, pi_i: Witness, pi_e: Witness,
+                                                modulus: U2048) -> Result<(), VerifyError> {
+    let (binary_vec, indices) = convert_key_value(&[key], &[value])?;
+    let (p_ones, p_zeros) = binary::get_bit_elems(&binary_vec, &indices);
+    return verify_elems_with_modulus(old_state, accumulator, p_ones, p_zeros, pi_i, pi_e, modulus);
+}
+
+/// Equivalent to commit in the 4096-bit group. The larger integer width also allows much larger
+/// products of accumulated elements than U2048.
+pub fn commit_4096<T: ValueType>(accumulator: wide::U4096, keys: &[usize], values: &[T]) -> Result<(wide::U4096, wide::U4096), VcError> {
+    let (binary_vec, indices) = convert_key_value(keys, values)?;
+    let (product, _) = get_bit_elems_4096(&binary_vec, &indices);
+    return Ok((wide::mod_exp(accumulator, product, wide::U4096::from_dec_str(wide::MODULUS).unwrap()), product));
+}
+
+/// Equivalent to open_at_key in the 4096-bit group.
+pub fn open_at_key_4096<T: ValueType>(old_state: wide::U4096, product: wide::U4096, key: usize, value: T) -> Result<(wide::Witness, wide::Witness), VcError> {
+    let (binary_vec, indices) = convert_key_value(&[key], &[value])?;
+    let (p_ones, p_zeros) = get_bit_elems_4096(&binary_vec, &indices);
+    let mem_wit = wide::mem_wit_create(old_state, product, p_ones).ok_or(VcError::NotCommitted)?;
+    let non_mem_wit = wide::non_mem_wit_create(old_state, product, p_zeros).ok_or(VcError::ValuePresent)?;
+    return Ok((wide::Witness::MemWit(mem_wit), wide::Witness::NonMemWit(non_mem_wit)));
+}
+
+/// Equivalent to verify_at_key in the 4096-bit group.
+pub fn verify_at_key_4096<T: ValueType>(old_state: wide::U4096, accumulator: wide::U4096, key: usize, value: T,
+                                        pi_i: wide::Witness, pi_e: wide::Witness) -> Result<(), VerifyError> {
+    let (binary_vec, indices) = convert_key_value(&[key], &[value])?;
+    let (p_ones, p_zeros) = get_bit_elems_4096(&binary_vec, &indices);
+    let (mem_wit, non_mem_wit) = match (pi_i, pi_e) {
+        (wide::Witness::MemWit(mem_wit), wide::Witness::NonMemWit(non_mem_wit)) => (mem_wit, non_mem_wit),
+        _ => return Err(VerifyError::BadWitness),
+    };
+
+    if !wide::verify_mem_wit(accumulator, mem_wit, p_ones) {
+        return Err(VerifyError::ValueMismatch);
+    }
+    if !wide::verify_non_mem_wit(old_state, accumulator, non_mem_wit, p_zeros) {
+        return Err(VerifyError::AccumulatorMismatch);
+    }
+    return Ok(());
+}
+
+/// Equivalent to binary::get_bit_elems in the 4096-bit group.
+fn get_bit_elems_4096(b: &[bool], i: &[usize]) -> (wide::U4096, wide::U4096) {
+    let mut p_ones = wide::U4096::from(1);
+    let mut p_zeros = wide::U4096::from(1);
+    for (bit, index) in b.iter().zip(i.iter()) {
+        let elem = wide::widen(binary::hash_index(*index));
+        if *bit {
+            p_ones *= elem;
+        }
+        else {
+            p_zeros *= elem;
+        }
+    }
+    return (p_ones, p_zeros);
+}
+
+/// Equivalent to commit in an arbitrary group of unknown order, such as a class group which
+/// requires no trusted setup.
+pub fn commit_in<G: group::Group, T: ValueType>(accumulator: G::Elem, keys: &[usize], values: &[T]) -> Result<(G::Elem, U2048), VcError> {
+    let (binary_vec, indices) = convert_key_value(keys, values)?;
+    return Ok(binary::commit_in::<G>(accumulator, &binary_vec, &indices));
+}
+
+/// Equivalent to open_at_key in an arbitrary group of unknown order. Returns the membership and
+/// non-membership witnesses.
+pub fn open_at_key_in<G: group::Group, T: ValueType>(old_state: G::Elem, product: U2048, key: usize, value: T)
+                                                     -> Result<(G::Elem, (U2048, bool, G::Elem)), VcError> {
+    let (binary_vec, indices) = convert_key_value(&[key], &[value])?;
+    let (p_ones, p_zeros) = binary::get_bit_elems(&binary_vec, &indices);
+    let mem_wit = group::mem_wit_create::<G>(old_state, product, p_ones).ok_or(VcError::NotCommitted)?;
+    let non_mem_wit = group::non_mem_wit_create::<G>(old_state, product, p_zeros).ok_or(VcError::ValuePresent)?;
+    return Ok((mem_wit, non_mem_wit));
+}
+
+/// Equivalent to verify_at_key in an arbitrary group of unknown order.
+pub fn verify_at_key_in<G: group::Group, T: ValueType>(old_state: G::Elem, accumulator: G::Elem, key: usize, value: T,
+                                                       mem_wit: G::Elem, non_mem_wit: (U2048, bool, G::Elem)) -> Result<(), VerifyError> {
+    let (binary_vec, indices) = convert_key_value(&[key], &[value])?;
+    let (p_ones, p_zeros) = binary::get_bit_elems(&binary_vec, &indices);
+    if !group::verify_mem_wit::<G>(accumulator, mem_wit, p_ones) {
+        return Err(VerifyError::ValueMismatch);
+    }
+    if !group::verify_non_mem_wit::<G>(old_state, accumulator, non_mem_wit, p_zeros) {
+        return Err(VerifyError::AccumulatorMismatch);
+    }
+    return Ok(());
+}
+
+/// Equivalent to commit but consults a prime cache before hashing the bit indices.
+#[cfg(feature = "std")]
+pub fn commit_with_cache<T: ValueType>(cache: &mut binary::PrimeCache, accumulator: U2048, keys: &[usize], values: &[T]) -> Result<(U2048, U2048), VcError> {
+    let (binary_vec, indices) = convert_key_value(keys, values)?;
+    return Ok(binary::commit_with_cache(cache, accumulator, &binary_vec, &indices));
+}
+
+/// Commit to a set of keys and corresponding values. The third value of the returned tuple is a
+/// (Wesolowski) proof of exponentiation that lets verifiers check the new state without recomputing
+/// the full exponentiation(see verify_commit).
+pub fn commit_with_proof<T: ValueType>(accumulator: U2048, keys: &[usize], values: &[T]) -> Result<(U2048, U2048, proofs::PoeProof), VcError> {
+    let (binary_vec, indices) = convert_key_value(keys, values)?;
+    return Ok(binary::commit_with_proof(accumulator, &binary_vec, &indices));
+}
+
+/// Verifies that "state" equals "accumulator" raised to the "product" power given a proof of
+/// exponentiation produced by commit_with_proof.
+pub fn verify_commit(accumulator: U2048, state: U2048, product: U2048, proof: proofs::PoeProof) -> bool {
+    return proofs::verify_poe(accumulator, product, state, proof);
 }
 
 /// Open a commitment for a value at a specific key. This function would be immediately called by a
 /// user following a relevant state commitment.
-pub fn open_at_key(old_state: U2048, product: U2048, key: usize, value: ValueType) -> (Witness, Witness) {
-    let (binary_vec, indices) = convert_key_value(&[key], &[value]);
-    return binary::batch_open(old_state, product, &binary_vec, &indices);
+/// NOTE: A key that was never committed holds the value 0. Opening it at any other value returns
+/// NotCommitted rather than panicking, and verifying it at any other value returns an error.
+pub fn open_at_key<T: ValueType>(old_state: U2048, product: U2048, key: usize, value: T) -> Result<(Witness, Witness), VcError> {
+    return open_at_key_with_modulus(old_state, product, key, value, U2048::from_dec_str(MODULUS).unwrap());
+}
+
+/// Equivalent to open_at_key but computes the witnesses with witnesses::mem_wit_create_ct and
+/// witnesses::non_mem_wit_create_ct, for openings whose values should not leak through the timing of
+/// the exponentiations(Ex: private balances). The result is identical but several times slower.
+/// NOTE: Only the exponentiations are constant-time(see the NOTE of each witness function).
+pub fn open_at_key_ct<T: ValueType>(old_state: U2048, product: U2048, key: usize, value: T) -> Result<(Witness, Witness), VcError> {
+    let (binary_vec, indices) = convert_key_value(&[key], &[value])?;
+    let (p_ones, p_zeros) = binary::get_bit_elems(&binary_vec, &indices);
+    let mem_wit = witnesses::mem_wit_create_ct(old_state, product, p_ones).ok_or(VcError::NotCommitted)?;
+    if subroutines::bezout(product, p_zeros).is_none() {
+        return Err(VcError::ValuePresent);
+    }
+    let non_mem_wit = witnesses::non_mem_wit_create_ct(old_state, product, p_zeros);
+    return Ok((Witness::MemWit(mem_wit), Witness::NonMemWit(non_mem_wit)));
+}
+
+/// Opens a commitment at every committed key at once. The membership witnesses are computed with
+/// the RootFactor algorithm in O(n log(n)) exponentiations rather than O(n^2). "old_state" is the
+/// state before the key-value pairs were committed.
+pub fn open_all<T: ValueType>(old_state: U2048, keys: &[usize], values: &[T]) -> Result<Vec<(Witness, Witness)>, VcError> {
+    let (binary_vec, indices) = convert_key_value(keys, values)?;
+    let (product, _) = binary::get_bit_elems(&binary_vec, &indices);
+    if keys.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut ones = Vec::with_capacity(keys.len());
+    let mut zeros = Vec::with_capacity(keys.len());
+    for (key, value) in keys.iter().zip(values.iter()) {
+        let (binary_vec, indices) = convert_key_value(&[*key], &[*value])?;
+        let (p_ones, p_zeros) = binary::get_bit_elems(&binary_vec, &indices);
+        ones.push(p_ones);
+        zeros.push(p_zeros);
+    }
+
+    let mem_wits = witnesses::create_all_mem_wit(old_state, &ones);
+    let openings = mem_wits
+        .into_iter()
+        .zip(zeros.into_iter())
+        .map(|(mem_wit, p_zeros)| (Witness::MemWit(mem_wit), Witness::NonMemWit(witnesses::non_mem_wit_create(old_state, product, p_zeros))))
+        .collect();
+    return Ok(openings);
+}
+
+/// Opens a commitment at each key independently(see open_at_key) and labels every opening with its
+/// key, so that the witnesses of each key can be handed to a different holder. Unlike open_all,
+/// "product" may also contain key-value pairs that are not opened.
+pub fn open_each<T: ValueType>(old_state: U2048, product: U2048, keys: &[usize], values: &[T]) -> Result<Vec<(usize, Witness, Witness)>, VcError> {
+    if keys.len() != values.len() {
+        return Err(VcError::LengthMismatch);
+    }
+    return keys
+        .iter()
+        .zip(values.iter())
+        .map(|(key, value)| {
+            let (pi_i, pi_e) = open_at_key(old_state, product, *key, *value)?;
+            return Ok((*key, pi_i, pi_e));
+        })
+        .collect();
+}
+
+/// Verify a commitment for a value at a specific key. Use is_ok() on the result if the reason for
+/// failure is irrelevant.
+pub fn verify_at_key<T: ValueType>(old_state: U2048, accumulator: U2048, key: usize, value: T, pi_i: Witness, pi_e: Witness) -> Result<(), VerifyError> {
+    let (binary_vec, indices) = convert_key_value(&[key], &[value])?;
+    return verify_bits(old_state, accumulator, &binary_vec, &indices, pi_i, pi_e);
+}
+
+/// Equivalent to verify_at_key but computes the elements of the key inline instead of building the
+/// bit and index vectors, so verification does not allocate(Ex: for no_std light clients).
+pub fn verify_at_key_noalloc<T: ValueType>(old_state: U2048, accumulator: U2048, key: usize, value: T, pi_i: Witness, pi_e: Witness) -> Result<(), VerifyError> {
+    let offset = core::mem::size_of::<T>()*8;
+    let start = key.checked_mul(offset).ok_or(VerifyError::KeyOutOfRange)?;
+    start.checked_add(offset).ok_or(VerifyError::KeyOutOfRange)?;
+
+    // Same bit ordering as to_binary: little endian bytes, most significant bit of each byte first
+    let value: u64 = value.into();
+    let mut p_ones = U2048::from(1);
+    let mut p_zeros = U2048::from(1);
+    for j in 0..offset {
+        let elem = binary::hash_index(start + j);
+        if (value >> (8*(j/8) + 7 - j%8)) & 1 == 1 {
+            p_ones *= elem;
+        }
+        else {
+            p_zeros *= elem;
+        }
+    }
+    return verify_elems(old_state, accumulator, p_ones, p_zeros, pi_i, pi_e);
+}
+
+/// Checks a membership witness for a key-value pair directly against the current state:
+/// witness^element = state where element is the product of the elements of the set bits(see
+/// get_key_value_elem). Unlike verify_at_key, no old state is needed.
+/// NOTE: Only the set bits are proven. A witness for a value also yields witnesses for every value
+/// whose set bits are a subset of its set bits, so use verify_at_key to prove the exact value.
+pub fn verify_membership<T: ValueType>(state: U2048, key: usize, value: T, witness: Witness) -> bool {
+    let elem = match get_key_value_elem(key, value) {
+        Ok(elem) => elem,
+        Err(_) => return false,
+    };
+    match witness {
+        Witness::MemWit(mem_wit) => {
+            return subroutines::mod_exp(mem_wit, elem, U2048::from_dec_str(MODULUS).unwrap()) == state;
+        },
+        Witness::NonMemWit(_) => {
+            return false;
+        },
+    }
+}
+
+/// Checks many independent membership witnesses(see verify_membership) against the current state with
+/// a single multi-exponentiation. Each witness is raised to its element times a 64-bit challenge
+/// r_i and the product is compared with state^sum(r_i). The challenges are derived by hashing the
+/// state and every item(Fiat-Shamir), so the check is deterministic and builds without "std".
+/// NOTE: A set with an invalid item passes with probability about 2^-64, except that witnesses which
+/// differ from valid ones by an element of small order(Ex: MODULUS-1) can cancel out and are not
+/// detected. Items whose exponent overflows a U2048(Ex: u64 values with many set bits) are checked
+/// individually.
+pub fn batch_verify_memberships<T: ValueType>(state: U2048, items: &[(usize, T, Witness)]) -> bool {
+    let mut elems: Vec<(U2048, U2048)> = Vec::with_capacity(items.len());
+    for (key, value, witness) in items.iter() {
+        let elem = match get_key_value_elem(*key, *value) {
+            Ok(elem) => elem,
+            Err(_) => return false,
+        };
+        match witness {
+            Witness::MemWit(mem_wit) => elems.push((*mem_wit, elem)),
+            Witness::NonMemWit(_) => return false,
+        }
+    }
+
+    let mut data = state.encode();
+    data.extend(elems.encode());
+    let seed = blake2_256(&data);
+    let mut pairs: Vec<(U2048, U2048)> = Vec::with_capacity(elems.len());
+    let mut challenge_sum = U2048::from(0);
+    for (i, (mem_wit, elem)) in elems.iter().enumerate() {
+        let mut bytes: [u8; 8] = [0; 8];
+        bytes.copy_from_slice(&blake2_256(&(seed, i as u64).encode())[..8]);
+        let challenge = U2048::from(u64::from_le_bytes(bytes));
+        match elem.checked_mul(challenge) {
+            Some(exp) => {
+                pairs.push((*mem_wit, exp));
+                challenge_sum = challenge_sum + challenge;
+            },
+            None => {
+                if !verify_membership(state, items[i].0, items[i].1, items[i].2) {
+                    return false;
+                }
+            },
+        }
+    }
+    let modulus = U2048::from_dec_str(MODULUS).unwrap();
+    return subroutines::multi_exp(&pairs) == subroutines::mod_exp(state, challenge_sum, modulus);
+}
+
+/// Proves that a single bit of the value committed at a key is set. "bit_position" indexes the bits in
+/// the order of to_binary, so the proof is for index key*offset + bit_position. Only the membership of
+/// that bit is revealed, not the rest of the value. The current state of the accumulator must equal
+/// old_state^product. Returns NotCommitted if the bit is not set.
+/// NOTE: An unset bit cannot be proven with a single membership witness(see open_at_key).
+pub fn open_bit<T: ValueType>(old_state: U2048, product: U2048, key: usize, bit_position: usize) -> Result<Witness, VcError> {
+    let elem = binary::hash_index(get_bit_index::<T>(key, bit_position)?);
+    let mem_wit = witnesses::mem_wit_create(old_state, product, elem).ok_or(VcError::NotCommitted)?;
+    return Ok(Witness::MemWit(mem_wit));
+}
+
+/// Verifies a proof produced by open_bit against the current state.
+pub fn verify_bit<T: ValueType>(accumulator: U2048, key: usize, bit_position: usize, proof: Witness) -> bool {
+    let index = match get_bit_index::<T>(key, bit_position) {
+        Ok(index) => index,
+        Err(_) => return false,
+    };
+    return binary::verify(accumulator, accumulator, true, index, proof);
+}
+
+/// Returns the index of a bit of the value at a key. Returns OutOfRange if "bit_position" is not
+/// within the bits of T.
+fn get_bit_index<T: ValueType>(key: usize, bit_position: usize) -> Result<usize, VcError> {
+    if bit_position >= bits_per_value::<T>() {
+        return Err(VcError::OutOfRange);
+    }
+    let indices = get_key_indices(key, bits_per_value::<T>())?;
+    return Ok(indices[bit_position]);
+}
+
+/// Open a commitment for the values at a set of keys. Produces a single aggregated pair of
+/// witnesses rather than one pair per key. Returns NotCommitted if any of the values is not the
+/// committed one.
+pub fn open_at_keys<T: ValueType>(old_state: U2048, product: U2048, keys: &[usize], values: &[T]) -> Result<(Witness, Witness), VcError> {
+    let (binary_vec, indices) = convert_key_value(keys, values)?;
+    return binary::batch_open_with_mode(old_state, product, &binary_vec, &indices, binary::CommitMode::SetBits)
+        .ok_or(VcError::NotCommitted);
+}
+
+/// Verify a commitment for the values at a set of keys given an aggregated pair of witnesses.
+pub fn verify_at_keys<T: ValueType>(old_state: U2048, accumulator: U2048, keys: &[usize], values: &[T], pi_i: Witness, pi_e: Witness) -> Result<(), VerifyError> {
+    let (binary_vec, indices) = convert_key_value(keys, values)?;
+    return verify_bits(old_state, accumulator, &binary_vec, &indices, pi_i, pi_e);
+}
+
+/// Proof that the values committed at a set of keys add up to a total: the values themselves along
+/// with a single aggregated opening for them.
+/// NOTE: Bit commitments are not additively homomorphic, so the values are revealed to the verifier.
+/// The proof only saves the verifier from checking one opening per key.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct SumProof<T: ValueType> {
+    pub values: Vec<T>,
+    pub pi_i: Witness,
+    pub pi_e: Witness,
+}
+
+/// Proves the total of the values committed at a set of keys. The current state of the accumulator
+/// must equal old_state^product. Returns NotCommitted if any of the values is not the committed one.
+pub fn prove_sum<T: ValueType>(old_state: U2048, product: U2048, keys: &[usize], values: &[T]) -> Result<SumProof<T>, VcError> {
+    let (pi_i, pi_e) = open_at_keys(old_state, product, keys, values)?;
+    return Ok(SumProof { values: values.to_vec(), pi_i, pi_e });
+}
+
+/// Verifies that the values committed at a set of keys add up to "claimed_total". The sum is computed
+/// in a u128 so it cannot overflow for any number of keys that fits in memory.
+pub fn verify_sum<T: ValueType>(old_state: U2048, accumulator: U2048, keys: &[usize], claimed_total: u128, proof: &SumProof<T>) -> bool {
+    let total: u128 = proof.values.iter().map(|value| (*value).into() as u128).sum();
+    if total != claimed_total {
+        return false;
+    }
+    return verify_at_keys(old_state, accumulator, keys, &proof.values, proof.pi_i, proof.pi_e).is_ok();
+}
+
+/// Equivalent to binary::batch_verify but reports which check failed.
+fn verify_bits(old_state: U2048, accumulator: U2048, b: &[bool], i: &[usize], pi_i: Witness, pi_e: Witness) -> Result<(), VerifyError> {
+    let (p_ones, p_zeros) = binary::get_bit_elems(b, i);
+    return verify_elems(old_state, accumulator, p_ones, p_zeros, pi_i, pi_e);
+}
+
+/// Verifies a pair of witnesses against the products of the "ones" and "zeros" elements.
+fn verify_elems(old_state: U2048, accumulator: U2048, p_ones: U2048, p_zeros: U2048, pi_i: Witness, pi_e: Witness) -> Result<(), VerifyError> {
+    return verify_elems_with_modulus(old_state, accumulator, p_ones, p_zeros, pi_i, pi_e, U2048::from_dec_str(MODULUS).unwrap());
+}
+
+fn verify_elems_with_modulus(old_state: U2048, accumulator: U2048, p_ones: U2048, p_zeros: U2048, pi_i: Witness, pi_e: Witness,
+                             modulus: U2048) -> Result<(), VerifyError> {
+    let (mem_wit, non_mem_wit) = match (pi_i, pi_e) {
+        (Witness::MemWit(mem_wit), Witness::NonMemWit(non_mem_wit)) => (mem_wit, non_mem_wit),
+        _ => return Err(VerifyError::BadWitness),
+    };
+    // Reject non-canonical witnesses(Ex: decoded from untrusted bytes) rather than reducing them
+    if !pi_i.validate(modulus) || !pi_e.validate(modulus) {
+        return Err(VerifyError::BadWitness);
+    }
+
+    if !witnesses::verify_mem_wit_with_modulus(accumulator, mem_wit, p_ones, modulus) {
+        return Err(VerifyError::ValueMismatch);
+    }
+    if !witnesses::verify_non_mem_wit_with_modulus(old_state, accumulator, non_mem_wit, p_zeros, modulus) {
+        return Err(VerifyError::AccumulatorMismatch);
+    }
+    return Ok(());
+}
+
+/// A single opening to be checked by verify_batch.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct VerifyItem<T: ValueType> {
+    pub old_state: U2048,
+    pub accumulator: U2048,
+    pub key: usize,
+    pub value: T,
+    pub pi_i: Witness,
+    pub pi_e: Witness,
+}
+
+/// Verifies a set of independent openings and returns false on the first failure. The primes for
+/// bit indices that are shared between items are only computed once.
+#[cfg(feature = "std")]
+pub fn verify_batch<T: ValueType>(items: &[VerifyItem<T>]) -> bool {
+    let mut cache = binary::PrimeCache::new();
+    for item in items.iter() {
+        let (binary_vec, indices) = match convert_key_value(&[item.key], &[item.value]) {
+            Ok(result) => result,
+            Err(_) => return false,
+        };
+        let (p_ones, p_zeros) = binary::get_bit_elems_cached(&binary_vec, &indices, &mut cache);
+        if verify_elems(item.old_state, item.accumulator, p_ones, p_zeros, item.pi_i, item.pi_e).is_err() {
+            return false;
+        }
+    }
+    return true;
+}
+
+/// Prove that a key is absent from a commitment, meaning that none of the bits reserved for the key
+/// have been accumulated. The current state of the accumulator must equal old_state^product.
+/// NOTE: A key that was committed with a value of zero is indistinguishable from an absent key.
+pub fn prove_absent<T: ValueType>(old_state: U2048, product: U2048, key: usize) -> Result<NonMembershipWitness, VcError> {
+    let elem = get_key_elem::<T>(key)?;
+    if subroutines::bezout(product, elem).is_none() {
+        return Err(VcError::KeyPresent);
+    }
+    return Ok(witnesses::non_mem_wit_create(old_state, product, elem));
+}
+
+/// Verify a proof that a key is absent from a commitment.
+pub fn verify_absent<T: ValueType>(old_state: U2048, accumulator: U2048, key: usize, proof: NonMembershipWitness) -> bool {
+    match get_key_elem::<T>(key) {
+        Ok(elem) => {
+            return witnesses::verify_non_mem_wit(old_state, accumulator, proof, elem);
+        },
+        Err(_) => {
+            return false;
+        },
+    }
+}
+
+/// Checks whether any of the bits reserved for a key have been accumulated in "product"(Ex: the
+/// product returned by commit). The state alone cannot be checked without a witness, so the product
+/// of the accumulated elements is required.
+/// NOTE: Only the set bits of a value are accumulated, so a key committed with a value of zero adds
+/// no elements and is indistinguishable from a key that was never written. Both return false.
+pub fn is_committed<T: ValueType>(product: U2048, key: usize) -> Result<bool, VcError> {
+    let elem = get_key_elem::<T>(key)?;
+    return Ok(subroutines::bezout(product, elem).is_none());
+}
+
+/// Prove that the value committed at a key is less than 2^bits without revealing it by showing that
+/// none of the higher bits have been accumulated. The current state of the accumulator must equal
+/// old_state^product.
+/// NOTE: As with prove_absent, an absent key is indistinguishable from a key committed with a value
+/// below 2^bits.
+pub fn prove_range<T: ValueType>(old_state: U2048, product: U2048, key: usize, value: T, bits: usize) -> Result<NonMembershipWitness, VcError> {
+    let value: u64 = value.into();
+    if bits < 64 && value >> bits != 0 {
+        return Err(VcError::OutOfRange);
+    }
+    let elem = get_high_bits_elem::<T>(key, bits)?;
+    if subroutines::bezout(product, elem).is_none() {
+        return Err(VcError::OutOfRange);
+    }
+    return Ok(witnesses::non_mem_wit_create(old_state, product, elem));
+}
+
+/// Verify a proof that the value committed at a key is less than 2^bits.
+pub fn verify_range<T: ValueType>(old_state: U2048, accumulator: U2048, key: usize, bits: usize, proof: NonMembershipWitness) -> bool {
+    match get_high_bits_elem::<T>(key, bits) {
+        Ok(elem) => {
+            return witnesses::verify_non_mem_wit(old_state, accumulator, proof, elem);
+        },
+        Err(_) => {
+            return false;
+        },
+    }
+}
+
+/// Prove that a value was never committed at a key by showing that the element of one of its set
+/// bits is not contained in the aggregated product. Returns the index of that bit along with the
+/// non-membership witness. The current state of the accumulator must equal old_state^product.
+/// NOTE: Fails if every set bit of the value is committed(Ex: the value matches or a value whose
+/// set bits are a superset was committed). A value of zero therefore can never be proven absent.
+pub fn prove_non_membership_at_key<T: ValueType>(old_state: U2048, product: U2048, key: usize, value: T) -> Result<(usize, NonMembershipWitness), VcError> {
+    let (binary_vec, indices) = convert_key_value(&[key], &[value])?;
+    for (&bit, &index) in binary_vec.iter().zip(indices.iter()) {
+        if bit {
+            let elem = binary::hash_index(index);
+            if subroutines::bezout(product, elem).is_some() {
+                return Ok((index, witnesses::non_mem_wit_create(old_state, product, elem)));
+            }
+        }
+    }
+    return Err(VcError::ValuePresent);
+}
+
+/// Verify a proof that a value was never committed at a key.
+pub fn verify_non_membership_at_key<T: ValueType>(old_state: U2048, accumulator: U2048, key: usize, value: T, proof: (usize, NonMembershipWitness)) -> bool {
+    let (index, witness) = proof;
+    match convert_key_value(&[key], &[value]) {
+        Ok((binary_vec, indices)) => {
+            // The proven index must be a set bit of the value
+            if !binary_vec.iter().zip(indices.iter()).any(|(&bit, &i)| bit && i == index) {
+                return false;
+            }
+            let elem = binary::hash_index(index);
+            return witnesses::verify_non_mem_wit(old_state, accumulator, witness, elem);
+        },
+        Err(_) => {
+            return false;
+        },
+    }
+}
+
+/// Update the values for a set of keys. Assumes the updated keys were previously committed.
+pub fn update<T: ValueType>(accumulator: U2048, old_state: U2048, agg: U2048, keys: &[usize], values: &[T]) -> Result<U2048, VcError> {
+    let (binary_vec, indices) = convert_key_value(keys, values)?;
+    return Ok(binary::update(accumulator, old_state, agg, &binary_vec, &indices));
+}
+
+/// Update the value of a single key. Only the bits that differ between the old and new value are
+/// hashed: bits that flip from 1 to 0 are deleted and bits that flip from 0 to 1 are added. The
+/// current state of the accumulator must equal old_state^agg where agg contains the elements of
+/// the old key-value pair.
+pub fn update_single<T: ValueType>(accumulator: U2048, old_state: U2048, agg: U2048, key: usize, old_value: T, new_value: T) -> Result<U2048, VcError> {
+    let indices = get_key_indices(key, core::mem::size_of::<T>()*8)?;
+    let old_bits = to_binary(old_value);
+    let new_bits = to_binary(new_value);
+
+    let mut additions: Vec<U2048> = Vec::new();
+    let mut deletions: Vec<U2048> = Vec::new();
+    for (i, &index) in indices.iter().enumerate() {
+        if old_bits[i] != new_bits[i] {
+            let elem = binary::hash_index(index);
+            if new_bits[i] {
+                additions.push(elem);
+            }
+            else {
+                deletions.push(elem);
+            }
+        }
+    }
+
+    // Delete cleared bits
+    let p_deletions = subroutines::prime_product(&deletions);
+    let mem_wit = witnesses::mem_wit_create(old_state, agg, p_deletions).ok_or(VcError::NotCommitted)?;
+    let new_state = accumulator::delete(accumulator, p_deletions, mem_wit).ok_or(VcError::NotCommitted)?;
+
+    // Add set bits
+    return Ok(add(new_state, subroutines::prime_product(&additions)));
+}
+
+/// Applies a set of (key, old value, new value) updates with a single modular exponentiation. Like
+/// update_single, only the flipped bits are hashed, but the deletions and additions of every update
+/// are folded into the exponent of old_state. The current state of the accumulator must equal
+/// old_state^agg where agg contains the elements of every old key-value pair.
+/// NOTE: The updated exponent (agg / deletions) * additions must fit in a U2048.
+pub fn batch_update<T: ValueType>(old_state: U2048, agg: U2048, updates: &[(usize, T, T)]) -> Result<U2048, VcError> {
+    let keys: Vec<usize> = updates.iter().map(|(key, _, _)| *key).collect();
+    validate_indices(&keys)?;
+
+    let mut additions: Vec<U2048> = Vec::new();
+    let mut deletions: Vec<U2048> = Vec::new();
+    for &(key, old_value, new_value) in updates.iter() {
+        let indices = get_key_indices(key, core::mem::size_of::<T>()*8)?;
+        let old_bits = to_binary(old_value);
+        let new_bits = to_binary(new_value);
+        for (i, &index) in indices.iter().enumerate() {
+            if old_bits[i] != new_bits[i] {
+                let elem = binary::hash_index(index);
+                if new_bits[i] {
+                    additions.push(elem);
+                }
+                else {
+                    deletions.push(elem);
+                }
+            }
+        }
+    }
+
+    let p_deletions = subroutines::prime_product(&deletions);
+    if agg % p_deletions != U2048::from(0) {
+        return Err(VcError::NotCommitted);
+    }
+    let exp = (agg / p_deletions) * subroutines::prime_product(&additions);
+    return Ok(subroutines::mod_exp(old_state, exp, U2048::from_dec_str(MODULUS).unwrap()));
+}
+
+/// Checks that "new_state" results from changing exactly the values at "keys" from "old_values" to
+/// "new_values" and nothing else. As in batch_update, the state before the transition must equal
+/// old_state^agg. The old values must also be exactly those committed in agg.
+pub fn verify_transition<T: ValueType>(old_state: U2048, new_state: U2048, agg: U2048, keys: &[usize], old_values: &[T], new_values: &[T]) -> bool {
+    if keys.len() != new_values.len() {
+        return false;
+    }
+    let (binary_vec, indices) = match convert_key_value(keys, old_values) {
+        Ok(converted) => converted,
+        Err(_) => return false,
+    };
+    let (p_ones, p_zeros) = binary::get_bit_elems(&binary_vec, &indices);
+    if agg % p_ones != U2048::from(0) || subroutines::bezout(agg, p_zeros).is_none() {
+        return false;
+    }
+
+    let updates: Vec<(usize, T, T)> = keys.iter().zip(old_values.iter().zip(new_values.iter()))
+        .map(|(&key, (&old_value, &new_value))| (key, old_value, new_value))
+        .collect();
+    match batch_update(old_state, agg, &updates) {
+        Ok(expected) => {
+            return expected == new_state;
+        },
+        Err(_) => {
+            return false;
+        },
+    }
+}
+
+/// Remove a key-value pair from a commitment. Only the set bits of the value were accumulated, so
+/// only their elements are deleted. The current state of the accumulator must equal old_state^agg
+/// where agg contains the elements of the key-value pair. The key is absent from the new state.
+pub fn remove_key<T: ValueType>(accumulator: U2048, old_state: U2048, agg: U2048, key: usize, value: T) -> Result<U2048, VcError> {
+    let elem = get_key_value_elem(key, value)?;
+    let mem_wit = witnesses::mem_wit_create(old_state, agg, elem).ok_or(VcError::NotCommitted)?;
+    return accumulator::delete(accumulator, elem, mem_wit).ok_or(VcError::NotCommitted);
+}
+
+/// Delete a key-value pair from a commitment given the inclusion proof(pi_i) of an opening for the
+/// pair. Unlike remove_key, this does not require the aggregated product. Returns an error if the
+/// witness does not match the key-value pair.
+pub fn delete<T: ValueType>(accumulator: U2048, witness: Witness, key: usize, value: T) -> Result<U2048, VcError> {
+    match witness {
+        Witness::MemWit(mem_wit) => {
+            let elem = get_key_value_elem(key, value)?;
+            return accumulator::delete(accumulator, elem, mem_wit).ok_or(VcError::BadWitness);
+        },
+        Witness::NonMemWit(_) => {
+            return Err(VcError::BadWitness);
+        },
+    }
+}
+
+/// Updates the opening of a key-value pair after untracked key-value pairs have been added to and/or
+/// removed from the commitment. "accumulator" is the state that the opening currently verifies
+/// against and "new_state" is the state after the changes. Additions are applied before removals.
+/// The updated opening continues to verify relative to the same "old_state".
+pub fn update_witness<T: ValueType>(accumulator: U2048, new_state: U2048, key: usize, value: T, pi_i: Witness, pi_e: Witness,
+                                    added: &[(usize, T)], removed: &[(usize, T)]) -> Result<(Witness, Witness), VcError> {
+    let (mem_wit, non_mem_wit) = match (pi_i, pi_e) {
+        (Witness::MemWit(mem_wit), Witness::NonMemWit(non_mem_wit)) => (mem_wit, non_mem_wit),
+        _ => return Err(VcError::BadWitness),
+    };
+
+    let (binary_vec, indices) = convert_key_value(&[key], &[value])?;
+    let (p_ones, p_zeros) = binary::get_bit_elems(&binary_vec, &indices);
+    let additions = get_pairs_elem(added)?;
+    let deletions = get_pairs_elem(removed)?;
+
+    // Handle the inclusion proof
+    let mut new_mem_wit = subroutines::mod_exp(mem_wit, additions, U2048::from_dec_str(MODULUS).unwrap());
+    new_mem_wit = subroutines::shamir_trick(new_mem_wit, new_state, p_ones, deletions).ok_or(VcError::BadWitness)?;
+
+    // Handle the exclusion proof
+    let new_non_mem_wit = witnesses::update_non_mem_wit(p_zeros, non_mem_wit, accumulator, additions, deletions)
+        .ok_or(VcError::BadWitness)?;
+
+    return Ok((Witness::MemWit(new_mem_wit), Witness::NonMemWit(new_non_mem_wit)));
+}
+
+/// Refreshes the opening of a key-value pair after the key-value pairs in "committed" are committed
+/// on top of "accumulator"(Ex: by the next block). Equivalent to update_witness without removals.
+pub fn refresh_witness_after_commit<T: ValueType>(accumulator: U2048, new_state: U2048, key: usize, value: T, pi_i: Witness, pi_e: Witness,
+                                                  committed: &[(usize, T)]) -> Result<(Witness, Witness), VcError> {
+    return update_witness(accumulator, new_state, key, value, pi_i, pi_e, committed, &[]);
+}
+
+/// Refreshes the opening of a key-value pair after the key-value pairs in "removed" are deleted from
+/// "accumulator". Equivalent to update_witness without additions. Returns an error if the opened
+/// pair itself was removed.
+pub fn refresh_witness_after_delete<T: ValueType>(accumulator: U2048, new_state: U2048, key: usize, value: T, pi_i: Witness, pi_e: Witness,
+                                                  removed: &[(usize, T)]) -> Result<(Witness, Witness), VcError> {
+    return update_witness(accumulator, new_state, key, value, pi_i, pi_e, &[], removed);
+}
+
+/// Aggregates the openings of several key-value pairs(each produced by open_at_key relative to the
+/// same "old_state") into a single opening that can be checked with verify_at_keys. "accumulator" is
+/// the current state of the accumulator.
+/// NOTE: The aggregated exponents grow with the number of keys, so only a small number of openings
+/// can be aggregated before U2048 overflows.
+pub fn aggregate_witnesses<T: ValueType>(accumulator: U2048, keys: &[usize], values: &[T], openings: &[(Witness, Witness)]) -> Result<(Witness, Witness), VcError> {
+    if keys.len() != openings.len() || openings.is_empty() {
+        return Err(VcError::LengthMismatch);
+    }
+    // Also rejects duplicate keys since their bit elements would not be coprime.
+    convert_key_value(keys, values)?;
+
+    let mut agg = None;
+    for ((key, value), opening) in keys.iter().zip(values.iter()).zip(openings.iter()) {
+        let (mem_wit, non_mem_wit) = match *opening {
+            (Witness::MemWit(mem_wit), Witness::NonMemWit(non_mem_wit)) => (mem_wit, non_mem_wit),
+            _ => return Err(VcError::BadWitness),
+        };
+        let (binary_vec, indices) = convert_key_value(&[*key], &[*value])?;
+        let (p_ones, p_zeros) = binary::get_bit_elems(&binary_vec, &indices);
+
+        agg = match agg {
+            None => Some((mem_wit, non_mem_wit, p_ones, p_zeros)),
+            Some((agg_mem_wit, agg_non_mem_wit, agg_ones, agg_zeros)) => {
+                let new_mem_wit = subroutines::shamir_trick(agg_mem_wit, mem_wit, agg_ones, p_ones)
+                    .ok_or(VcError::BadWitness)?;
+                let new_non_mem_wit = witnesses::agg_non_mem_wit(accumulator, agg_non_mem_wit, non_mem_wit, agg_zeros, p_zeros)
+                    .ok_or(VcError::BadWitness)?;
+                Some((new_mem_wit, new_non_mem_wit, agg_ones * p_ones, agg_zeros * p_zeros))
+            },
+        };
+    }
+
+    let (mem_wit, non_mem_wit, _, _) = agg.unwrap();
+    return Ok((Witness::MemWit(mem_wit), Witness::NonMemWit(non_mem_wit)));
+}
+
+/// Removes an element from an aggregated witness. Raising a membership witness for a product of
+/// elements to "element_to_remove" gives the membership witness for the remaining product. For a
+/// non-membership witness (a, B) of a product, (a, B^element_to_remove) is the non-membership
+/// witness for the remaining product since A^a * (B^element_to_remove)^(remaining) = A^a * B^product.
+/// The elements of a key are the products from binary::get_bit_elems for its bits(the set bits for
+/// pi_i and the unset bits for pi_e).
+/// NOTE: The remaining product is not needed to compute the witness, and an element that was not
+/// part of the aggregate is not detected. The result then simply fails to verify.
+pub fn disaggregate(aggregate: Witness, element_to_remove: U2048) -> Witness {
+    let modulus = U2048::from_dec_str(MODULUS).unwrap();
+    match aggregate {
+        Witness::MemWit(mem_wit) => {
+            return Witness::MemWit(witnesses::disaggregate_mem_wit(mem_wit, element_to_remove, modulus));
+        },
+        Witness::NonMemWit((a, sign_a, B)) => {
+            return Witness::NonMemWit((a, sign_a, subroutines::mod_exp(B, element_to_remove, modulus)));
+        },
+    }
+}
+
+/// Commit to a set of keys and corresponding byte-string values. Each value is padded to "width"
+/// bytes (see convert_key_bytes).
+pub fn commit_bytes(accumulator: U2048, keys: &[usize], values: &[Vec<u8>], width: usize) -> Result<(U2048, U2048), VcError> {
+    let (binary_vec, indices) = convert_key_bytes(keys, values, width)?;
+    return Ok(binary::commit(accumulator, &binary_vec, &indices));
 }
 
-/// Verify a commitment for a value at a specific key.
-pub fn verify_at_key(old_state: U2048, accumulator: U2048, key: usize, value: ValueType, pi_i: Witness, pi_e: Witness) -> bool {
-    let (binary_vec, indices) = convert_key_value(&[key], &[value]);
-    return binary::batch_verify(old_state, accumulator, &binary_vec, &indices, pi_i, pi_e);
-}
+/// Open a commitment for a byte-string value at a specific key. Returns NotCommitted if "value" is
+/// not the committed value.
+pub fn open_at_key_bytes(old_state: U2048, product: U2048, key: usize, value: &[u8], width: usize) -> Result<(Witness, Witness), VcError> {
+    let (binary_vec, indices) = convert_key_bytes(&[key], &[value.to_vec()], width)?;
+    return binary::batch_open_with_mode(old_state, product, &binary_vec, &indices, binary::CommitMode::SetBits)
+        .ok_or(VcError::NotCommitted);
+}
+
+/// Verify a commitment for a byte-string value at a specific key.
+pub fn verify_at_key_bytes(old_state: U2048, accumulator: U2048, key: usize, value: &[u8], width: usize, pi_i: Witness, pi_e: Witness) -> bool {
+    match convert_key_bytes(&[key], &[value.to_vec()], width) {
+        Ok((binary_vec, indices)) => {
+            return binary::batch_verify(old_state, accumulator, &binary_vec, &indices, pi_i, pi_e);
+        },
+        Err(_) => {
+            return false;
+        },
+    }
+}
+
+/// Converts key-value pairs with byte-string values into a binary representation along with
+/// corresponding indices. Each key reserves width*8 consecutive indices.
+/// NOTE: Values shorter than "width" are padded with trailing zero bytes and values longer than
+/// "width" are truncated. As a result, an empty value is indistinguishable from a value of "width"
+/// zero bytes, and trailing zero bytes are not preserved.
+pub fn convert_key_bytes(keys: &[usize], values: &[Vec<u8>], width: usize) -> Result<(Vec<bool>, Vec<usize>), VcError> {
+    if keys.len() != values.len() {
+        return Err(VcError::LengthMismatch);
+    }
+    // Every key reserves the same number of indices, so ranges overlap iff a key is repeated.
+    validate_indices(keys)?;
+    let offset = width.checked_mul(8).ok_or(VcError::KeyOutOfRange)?;
+    let mut binary_vec: Vec<bool> = [].to_vec();
+    let mut indices: Vec<usize> = [].to_vec();
+    for (i, value) in values.iter().enumerate() {
+        let mut padded = value.clone();
+        padded.resize(width, 0);
+        let mut value_vec = BitVec::from_bytes(&padded).iter().collect::<Vec<bool>>();
+        let mut index_vec = get_key_indices(keys[i], offset)?;
+        binary_vec.append(&mut value_vec);
+        indices.append(&mut index_vec);
+    }
+    return Ok((binary_vec, indices));
+}
+
+/// Commit to an arbitrary-length byte string laid out across consecutive bit indices beginning at
+/// "start". Unlike commit_bytes, the length is committed as well, so a truncated or zero-extended
+/// value fails verification (see convert_length_prefixed).
+pub fn commit_length_prefixed(accumulator: U2048, start: usize, value: &[u8]) -> Result<(U2048, U2048), VcError> {
+    let (binary_vec, indices) = convert_length_prefixed(start, value)?;
+    return Ok(binary::commit(accumulator, &binary_vec, &indices));
+}
+
+/// Open a commitment for a length-prefixed byte string beginning at "start". Returns NotCommitted if
+/// "value" is not the committed value(Ex: it is truncated).
+pub fn open_length_prefixed(old_state: U2048, product: U2048, start: usize, value: &[u8]) -> Result<(Witness, Witness), VcError> {
+    let (binary_vec, indices) = convert_length_prefixed(start, value)?;
+    return binary::batch_open_with_mode(old_state, product, &binary_vec, &indices, binary::CommitMode::SetBits)
+        .ok_or(VcError::NotCommitted);
+}
+
+/// Verify a commitment for a length-prefixed byte string beginning at "start".
+pub fn verify_length_prefixed(old_state: U2048, accumulator: U2048, start: usize, value: &[u8], pi_i: Witness, pi_e: Witness) -> Result<(), VerifyError> {
+    let (binary_vec, indices) = convert_length_prefixed(start, value)?;
+    return verify_bits(old_state, accumulator, &binary_vec, &indices, pi_i, pi_e);
+}
+
+/// Converts a byte string into the bits of its length(as a u32) followed by the bits of its bytes,
+/// along with the consecutive indices beginning at "start". A value reserves 32 + 8*len indices,
+/// so the caller must keep the index ranges of different values disjoint.
+/// NOTE: Values longer than u32::max_value() bytes are rejected with KeyOutOfRange.
+pub fn convert_length_prefixed(start: usize, value: &[u8]) -> Result<(Vec<bool>, Vec<usize>), VcError> {
+    if value.len() > u32::max_value() as usize {
+        return Err(VcError::KeyOutOfRange);
+    }
+    let offset = value.len().checked_mul(8).and_then(|bits| bits.checked_add(32)).ok_or(VcError::KeyOutOfRange)?;
+    let end = start.checked_add(offset).ok_or(VcError::KeyOutOfRange)?;
+    let mut binary_vec = to_binary(value.len() as u32);
+    binary_vec.extend(BitVec::from_bytes(value).iter());
+    return Ok((binary_vec, (start..end).collect()));
+}
+
+/// A byte string of arbitrary length(Ex: a 256 bit token amount) stored across consecutive u8 keys
+/// beginning at "base_key". The value reserves BigValue::slots(len) keys: 4 keys for the length(as a
+/// little endian u32) followed by one key per byte, so a truncated or zero-extended value fails
+/// verification. The bit indices are the same as those of convert_length_prefixed.
+/// NOTE: The caller must keep the keys of a BigValue disjoint from every other key of the commitment.
+/// The product of the commitment must still fit in a U2048, which bounds the number of set bits of
+/// the value. The value is therefore opened one key at a time, since a single opening of every bit
+/// would exceed a U2048.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BigValue {
+    pub base_key: usize,
+}
+
+impl BigValue {
+    pub fn new(base_key: usize) -> Self {
+        return BigValue { base_key };
+    }
+
+    /// Returns the number of u8 keys reserved by a value of "len" bytes. A zero-length value still
+    /// reserves the keys of its length, which distinguishes it from a value of zero bytes.
+    pub fn slots(len: usize) -> usize {
+        return 4 + len;
+    }
+
+    /// Commit to "value" beginning at the base key.
+    pub fn commit(&self, accumulator: U2048, value: &[u8]) -> Result<(U2048, U2048), VcError> {
+        let (keys, values) = self.get_slots(value)?;
+        return commit(accumulator, &keys, &values);
+    }
+
+    /// Open the whole value beginning at the base key. Returns one opening per key(see slots).
+    /// Like open_at_key, returns NotCommitted if "value" is not the committed value(Ex: it is shorter).
+    pub fn open_big(&self, old_state: U2048, product: U2048, value: &[u8]) -> Result<Vec<(Witness, Witness)>, VcError> {
+        let (keys, values) = self.get_slots(value)?;
+        let mut openings: Vec<(Witness, Witness)> = Vec::with_capacity(keys.len());
+        for (key, value) in keys.iter().zip(values.iter()) {
+            let (binary_vec, indices) = convert_key_value(&[*key], &[*value])?;
+            let opening = binary::batch_open_with_mode(old_state, product, &binary_vec, &indices, binary::CommitMode::SetBits);
+            openings.push(opening.ok_or(VcError::NotCommitted)?);
+        }
+        return Ok(openings);
+    }
+
+    /// Verify the whole value beginning at the base key given the openings returned by open_big. A
+    /// value of a different length than the committed one fails verification, even if it is a prefix
+    /// of the committed value.
+    pub fn verify_big(&self, old_state: U2048, accumulator: U2048, value: &[u8], openings: &[(Witness, Witness)]) -> Result<(), VerifyError> {
+        let (keys, values) = self.get_slots(value)?;
+        if keys.len() != openings.len() {
+            return Err(VerifyError::LengthMismatch);
+        }
+        for ((key, value), (pi_i, pi_e)) in keys.iter().zip(values.iter()).zip(openings.iter()) {
+            verify_at_key(old_state, accumulator, *key, *value, *pi_i, *pi_e)?;
+        }
+        return Ok(());
+    }
+
+    /// Returns the keys reserved by "value" along with the byte held by each key.
+    /// NOTE: Values longer than u32::max_value() bytes are rejected with KeyOutOfRange.
+    fn get_slots(&self, value: &[u8]) -> Result<(Vec<usize>, Vec<u8>), VcError> {
+        if value.len() > u32::max_value() as usize {
+            return Err(VcError::KeyOutOfRange);
+        }
+        let end = self.base_key.checked_add(BigValue::slots(value.len())).ok_or(VcError::KeyOutOfRange)?;
+        let mut values: Vec<u8> = (value.len() as u32).to_le_bytes().to_vec();
+        values.extend_from_slice(value);
+        return Ok(((self.base_key..end).collect(), values));
+    }
+}
+
+/// Commit to several values packed end-to-end within the index range of a single key. A key with
+/// n fields reserves n*size_of::<T>()*8 consecutive indices, so field j of key k begins at index
+/// (k*n + j)*size_of::<T>()*8. Every key in a commitment must be packed with the same field count.
+pub fn commit_packed<T: ValueType>(accumulator: U2048, key: usize, fields: &[T]) -> Result<(U2048, U2048), VcError> {
+    let keys = get_packed_keys(key, fields.len(), 0..fields.len())?;
+    return commit(accumulator, &keys, fields);
+}
+
+/// Open a single field of a key that was committed with commit_packed.
+pub fn open_packed<T: ValueType>(old_state: U2048, product: U2048, key: usize, field_count: usize, field: usize, value: T) -> Result<(Witness, Witness), VcError> {
+    let keys = get_packed_keys(key, field_count, field..field + 1)?;
+    return open_at_key(old_state, product, keys[0], value);
+}
+
+/// Verify a single field of a key that was committed with commit_packed.
+pub fn verify_packed<T: ValueType>(old_state: U2048, accumulator: U2048, key: usize, field_count: usize, field: usize, value: T,
+                                   pi_i: Witness, pi_e: Witness) -> Result<(), VerifyError> {
+    let keys = get_packed_keys(key, field_count, field..field + 1)?;
+    return verify_at_key(old_state, accumulator, keys[0], value, pi_i, pi_e);
+}
+
+/// Maps the fields of a packed key to the equivalent unpacked keys(key*field_count + field).
+fn get_packed_keys(key: usize, field_count: usize, fields: core::ops::Range<usize>) -> Result<Vec<usize>, VcError> {
+    if fields.end > field_count {
+        return Err(VcError::KeyOutOfRange);
+    }
+    let start = key.checked_mul(field_count).ok_or(VcError::KeyOutOfRange)?;
+    return fields.map(|field| start.checked_add(field).ok_or(VcError::KeyOutOfRange)).collect();
+}
+
+/// Converts key-value pairs into a binary representation of the values along with corresponding
+/// indices. Each key reserves size_of::<T>()*8 consecutive indices.
+pub fn convert_key_value<T: ValueType>(keys: &[usize], values: &[T]) -> Result<(Vec<bool>, Vec<usize>), VcError> {
+    if keys.len() != values.len() {
+        return Err(VcError::LengthMismatch);
+    }
+    if keys.iter().any(|&key| key > max_key::<T>()) {
+        return Err(VcError::KeyOutOfRange);
+    }
+    // Every key reserves the same number of indices, so ranges overlap iff a key is repeated.
+    validate_indices(keys)?;
+    let offset = bits_per_value::<T>();
+    let mut binary_vec: Vec<bool> = Vec::with_capacity(keys.len() * offset);
+    let mut indices: Vec<usize> = Vec::with_capacity(keys.len() * offset);
+    for (i, &value) in values.iter().enumerate() {
+        let mut value_vec = to_binary(value);
+        let mut index_vec = get_key_indices(keys[i], offset)?;
+        binary_vec.append(&mut value_vec);
+        indices.append(&mut index_vec);
+    }
+    return Ok((binary_vec, indices));
+}
+
+/// Equivalent to convert_key_value but lazily yields (index, bit) pairs in the same order instead of
+/// collecting the bits and indices into two vectors. The keys are validated before the iterator is
+/// returned, so iterating cannot fail.
+pub fn convert_key_value_iter<'a, T: ValueType>(keys: &'a [usize], values: &'a [T]) -> Result<impl Iterator<Item = (usize, bool)> + 'a, VcError> {
+    if keys.len() != values.len() {
+        return Err(VcError::LengthMismatch);
+    }
+    if keys.iter().any(|&key| key > max_key::<T>()) {
+        return Err(VcError::KeyOutOfRange);
+    }
+    validate_indices(keys)?;
+    let offset = bits_per_value::<T>();
+    // max_key guarantees that key*offset + offset fits in a usize.
+    return Ok(keys.iter().zip(values.iter()).flat_map(move |(&key, &value)| {
+        to_binary(value).into_iter().enumerate().map(move |(i, bit)| (key * offset + i, bit))
+    }));
+}
+
+/// Returns the number of indices reserved for each key when committing values of type T.
+pub const fn bits_per_value<T>() -> usize {
+    return core::mem::size_of::<T>()*8;
+}
+
+/// Returns the largest key that can be committed with values of type T. The end(exclusive) of the
+/// index range reserved for a key must fit in a usize.
+pub fn max_key<T: ValueType>() -> usize {
+    return usize::max_value() / bits_per_value::<T>() - 1;
+}
+
+/// Checks that no index(or key) is claimed more than once. Runs in O(n log(n)).
+pub fn validate_indices(indices: &[usize]) -> Result<(), VcError> {
+    let mut sorted = indices.to_vec();
+    sorted.sort_unstable();
+    if sorted.windows(2).any(|pair| pair[0] == pair[1]) {
+        return Err(VcError::OverlappingIndices);
+    }
+    return Ok(());
+}
+
+/// Returns the product of the accumulated elements for a set of key-value pairs.
+fn get_pairs_elem<T: ValueType>(pairs: &[(usize, T)]) -> Result<U2048, VcError> {
+    let keys: Vec<usize> = pairs.iter().map(|(key, _)| *key).collect();
+    let values: Vec<T> = pairs.iter().map(|(_, value)| *value).collect();
+    let (binary_vec, indices) = convert_key_value(&keys, &values)?;
+    let (elem, _) = binary::get_bit_elems(&binary_vec, &indices);
+    return Ok(elem);
+}
+
+/// Returns the product of the accumulator elements of every index reserved for a key.
+fn get_key_elem<T: ValueType>(key: usize) -> Result<U2048, VcError> {
+    let indices = get_key_indices(key, core::mem::size_of::<T>()*8)?;
+    let bits: Vec<bool> = indices.iter().map(|_| false).collect();
+    let (_, elem) = binary::get_bit_elems(&bits, &indices);
+    return Ok(elem);
+}
+
+/// Returns the product of the accumulator elements of the bits of a key with weight at least 2^bits.
+/// Bit k of a value is stored at position 8*(k/8) + 7 - k%8(see to_binary).
+fn get_high_bits_elem<T: ValueType>(key: usize, bits: usize) -> Result<U2048, VcError> {
+    let width = core::mem::size_of::<T>()*8;
+    let indices = get_key_indices(key, width)?;
+    let elems: Vec<U2048> = (bits..width)
+        .map(|k| binary::hash_index(indices[8*(k/8) + 7 - k%8]))
+        .collect();
+    return Ok(subroutines::prime_product(&elems));
+}
+
+/// Returns the "offset" consecutive indices reserved for a key.
+fn get_key_indices(key: usize, offset: usize) -> Result<Vec<usize>, VcError> {
+    let start = key.checked_mul(offset).ok_or(VcError::KeyOutOfRange)?;
+    let end = start.checked_add(offset).ok_or(VcError::KeyOutOfRange)?;
+    return Ok((start..end).collect());
+}
+
+/// Converts an element to a binary representation. Emits size_of::<T>()*8 bits.
+/// Bit ordering: bytes are laid out in little endian order and the bits of each byte are laid out
+/// most significant bit first. For example, 6u8 -> [0, 0, 0, 0, 0, 1, 1, 0] and
+/// 1u16 -> [0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0].
+pub fn to_binary<T: ValueType>(elem: T) -> Vec<bool> {
+    let byte_vec = elem.to_le_byte_vec();
+    let bv = BitVec::from_bytes(&byte_vec);
+    return bv.iter().collect::<Vec<bool>>();
+}
+
+/// Reconstructs an element from its binary representation. Exact inverse of to_binary and uses the
+/// same bit ordering.
+pub fn from_binary<T: ValueType>(bits: &[bool]) -> T {
+    let bv: BitVec = bits.iter().cloned().collect();
+    return T::from_le_byte_slice(&bv.to_bytes());
+}
+
+/// Bit orderings for converting an element to a binary representation. For an element with n bits
+/// where bit k has weight 2^k:
+/// - Mixed: bytes in little endian order with the most significant bit of each byte first, so bit k
+///   maps to position 8*(k/8) + 7 - k%8. This is the ordering used by commitments(see to_binary).
+/// - Little: least significant bit first, so bit k maps to position k.
+/// - Big: most significant bit first, so bit k maps to position n - 1 - k.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Endianness {
+    Mixed,
+    Little,
+    Big,
+}
+
+/// Converts an element to a binary representation using the given bit ordering.
+pub fn to_binary_with<T: ValueType>(elem: T, endianness: Endianness) -> Vec<bool> {
+    let n = core::mem::size_of::<T>()*8;
+    let value: u64 = elem.into();
+    match endianness {
+        Endianness::Mixed => {
+            return to_binary(elem);
+        },
+        Endianness::Little => {
+            return (0..n).map(|k| (value >> k) & 1 == 1).collect();
+        },
+        Endianness::Big => {
+            return (0..n).rev().map(|k| (value >> k) & 1 == 1).collect();
+        },
+    }
+}
+
+/// Reconstructs an element from its binary representation using the given bit ordering. Exact
+/// inverse of to_binary_with.
+pub fn from_binary_with<T: ValueType>(bits: &[bool], endianness: Endianness) -> T {
+    let mut value: u64 = 0;
+    match endianness {
+        Endianness::Mixed => {
+            return from_binary(bits);
+        },
+        Endianness::Little => {
+            for (k, &bit) in bits.iter().enumerate() {
+                if bit {
+                    value |= 1 << k;
+                }
+            }
+        },
+        Endianness::Big => {
+            for &bit in bits.iter() {
+                value = (value << 1) | (bit as u64);
+            }
+        },
+    }
+    return T::from_le_byte_slice(&value.to_le_bytes());
+}
+
+/// Quick helper function that gets the product of the accumulated elements for a given
+/// key-value pair. Only the set bits of the value contribute(so a value of 0 yields 1), which
+/// matches the product returned by commit and the membership product proven by open_at_key.
+/// Bit k of the value is stored at index key*size_of::<T>()*8 + 8*(k/8) + 7 - k%8(see to_binary),
+/// so the high bit of a u8 maps to the first index of the key.
+pub fn get_key_value_elem<T: ValueType>(key: usize, value: T) -> Result<U2048, VcError> {
+    let (binary_vec, indices) = convert_key_value(&[key], &[value])?;
+    let (elem, _) = binary::get_bit_elems(&binary_vec, &indices);
+    return Ok(elem);
+}
+
+/// Returns the bit indices of the set bits alongside their hashed primes so that commitments can be
+/// cross-checked against an independent accumulator implementation. The product of the returned
+/// primes equals the product returned by commit.
+pub fn element_primes<T: ValueType>(keys: &[usize], values: &[T]) -> Result<Vec<(usize, U2048)>, VcError> {
+    let (binary_vec, indices) = convert_key_value(keys, values)?;
+    return Ok(binary_vec
+        .iter()
+        .zip(indices.iter())
+        .filter(|(bit, _)| **bit)
+        .map(|(_, index)| (*index, binary::hash_index(*index)))
+        .collect());
+}
+
+/// Recomputes the product of the accumulated elements of a set of key-value pairs without the
+/// exponentiation. Equals the product returned by commit for the same pairs.
+pub fn product_of<T: ValueType>(keys: &[usize], values: &[T]) -> Result<U2048, VcError> {
+    let primes: Vec<U2048> = element_primes(keys, values)?.into_iter().map(|(_, prime)| prime).collect();
+    return Ok(subroutines::prime_product(&primes));
+}
+
+/// Checks that a claimed product is exactly the product of the given key-value pairs, Ex: so that a
+/// challenger can show that a committed product does not match the claimed pairs in a fraud proof.
+/// Invalid keys never match.
+pub fn matches_product<T: ValueType>(claimed: U2048, keys: &[usize], values: &[T]) -> bool {
+    match product_of(keys, values) {
+        Ok(product) => {
+            return product == claimed;
+        },
+        Err(_) => {
+            return false;
+        },
+    }
+}
+
+/// Equivalent to get_key_value_elem but consults a prime cache before hashing the bit indices.
+#[cfg(feature = "std")]
+pub fn get_key_value_elem_cached<T: ValueType>(key: usize, value: T, cache: &mut binary::PrimeCache) -> Result<U2048, VcError> {
+    let (binary_vec, indices) = convert_key_value(&[key], &[value])?;
+    let (elem, _) = binary::get_bit_elems_cached(&binary_vec, &indices, cache);
+    return Ok(elem);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use accumulator::group::Group;
+
+    #[test]
+    fn test_to_binary() {
+        let elem: u8 = 6;
+        let bv = to_binary(elem);
+        assert_eq!(bv, vec![false, false, false, false, false, true, true, false]);
+    }
+
+    #[test]
+    fn test_commit() {
+        let accumulator: U2048 = U2048::from(2);
+        let keys = [0, 1];
+        let values: Vec<u8> = vec![4, 7];
+
+        let (new_accumulator, _) = commit(accumulator, &keys, &values).unwrap();
+
+        // Manual check
+        let check_product = binary::hash_index(5)
+            * binary::hash_index(13)
+            * binary::hash_index(14)
+            * binary::hash_index(15);
+
+        assert_eq!(new_accumulator, subroutines::mod_exp(U2048::from(2), U2048::from(check_product), U2048::from_dec_str(MODULUS).unwrap()));
+    }
+
+    #[test]
+    fn test_convert() {
+        let keys = vec![0, 1];
+        let values: Vec<u8> = vec![4, 7];
+        let (binary_vec, indices) = convert_key_value(&keys, &values).unwrap();
+        assert_eq!(binary_vec, vec![false, false, false, false, false, true, false, false, false, false, false, false,
+            false, true, true, true]);
+        assert_eq!(indices, vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+    }
+
+    #[test]
+    fn test_convert_key_value_iter() {
+        let keys = vec![3, 0, 1];
+        let values: Vec<u16> = vec![4, 513, 65535];
+        let (binary_vec, indices) = convert_key_value(&keys, &values).unwrap();
+        let pairs: Vec<(usize, bool)> = convert_key_value_iter(&keys, &values).unwrap().collect();
+        assert_eq!(pairs, indices.into_iter().zip(binary_vec.into_iter()).collect::<Vec<(usize, bool)>>());
+
+        assert_eq!(convert_key_value_iter(&[0, 1], &[4u8]).err(), Some(VcError::LengthMismatch));
+        assert_eq!(convert_key_value_iter(&[max_key::<u8>() + 1], &[4u8]).err(), Some(VcError::KeyOutOfRange));
+        assert_eq!(convert_key_value_iter(&[1, 1], &[4u8, 7]).err(), Some(VcError::OverlappingIndices));
+        assert_eq!(convert_key_value_iter::<u8>(&[], &[]).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_max_key() {
+        assert_eq!(bits_per_value::<u8>(), 8);
+        assert_eq!(bits_per_value::<u64>(), 64);
+        assert_eq!(max_key::<u8>(), usize::max_value() / 8 - 1);
+
+        let (_, indices) = convert_key_value(&[max_key::<u8>()], &[4u8]).unwrap();
+        assert_eq!(*indices.last().unwrap(), usize::max_value() - 8);
+        assert_eq!(convert_key_value(&[max_key::<u8>() + 1], &[4u8]), Err(VcError::KeyOutOfRange));
+        assert_eq!(convert_key_value(&[max_key::<u64>()], &[4u64]).is_ok(), true);
+        assert_eq!(convert_key_value(&[0, max_key::<u64>() + 1], &[4u64, 7]), Err(VcError::KeyOutOfRange));
+    }
+
+    #[test]
+    fn test_key_overflow() {
+        // The bound scales with the pointer width, so the same keys overflow on 32-bit targets(wasm)
+        let last = max_key::<u32>();
+        let (_, indices) = convert_key_value(&[last], &[1u32]).unwrap();
+        assert_eq!(*indices.last().unwrap(), usize::max_value() - 32);
+
+        // Wrapping would map these keys onto the indices of small keys
+        let accumulator: U2048 = U2048::from(2);
+        for &key in [last + 1, usize::max_value() / 32 + 1, usize::max_value()].iter() {
+            assert_eq!(convert_key_value(&[key], &[1u32]), Err(VcError::KeyOutOfRange));
+            assert_eq!(convert_key_value_iter(&[key], &[1u32]).err(), Some(VcError::KeyOutOfRange));
+            assert_eq!(convert_key_bytes(&[key], &[vec![1; 4]], 4), Err(VcError::KeyOutOfRange));
+            assert_eq!(commit(accumulator, &[0, key], &[1u32, 1]), Err(VcError::KeyOutOfRange));
+            assert_eq!(update_single(accumulator, accumulator, U2048::from(1), key, 1u32, 2u32), Err(VcError::KeyOutOfRange));
+            assert_eq!(batch_update(accumulator, U2048::from(1), &[(key, 1u32, 2u32)]), Err(VcError::KeyOutOfRange));
+            assert_eq!(verify_at_key_noalloc(accumulator, accumulator, key, 1u32, Witness::MemWit(accumulator), Witness::MemWit(accumulator)),
+                       Err(VerifyError::KeyOutOfRange));
+        }
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn test_large_keys() {
+        // Account identifiers well beyond the u32 range map to their own index ranges
+        let keys = [1usize << 40, max_key::<u8>() - 1, max_key::<u8>()];
+        let values: Vec<u8> = vec![5, 7, 255];
+        let (_, indices) = convert_key_value(&keys, &values).unwrap();
+        assert_eq!(indices[..8].to_vec(), ((1usize << 43)..(1usize << 43) + 8).collect::<Vec<usize>>());
+        assert_eq!(indices[8], usize::max_value() - 23);
+        assert_eq!(indices[16], usize::max_value() - 15);
+        assert_eq!(validate_indices(&indices), Ok(()));
+
+        let accumulator: U2048 = U2048::from(2);
+        let (state, product) = commit(accumulator, &keys, &values).unwrap();
+        let (pi_i, pi_e) = open_at_key(accumulator, product, 1usize << 40, 5u8).unwrap();
+        assert_eq!(verify_at_key(accumulator, state, 1usize << 40, 5u8, pi_i, pi_e), Ok(()));
+        assert!(verify_at_key(accumulator, state, (1usize << 40) + 1, 5u8, pi_i, pi_e).is_err());
+    }
+
+    #[test]
+    fn test_convert_invalid_input() {
+        assert_eq!(convert_key_value(&[0, 1], &[4u8]), Err(VcError::LengthMismatch));
+        assert_eq!(convert_key_value(&[0], &[4u8, 7]), Err(VcError::LengthMismatch));
+        assert_eq!(convert_key_value(&[usize::max_value()], &[4u8]), Err(VcError::KeyOutOfRange));
+        assert_eq!(convert_key_bytes(&[0, 1], &[vec![4]], 1), Err(VcError::LengthMismatch));
+
+        let accumulator: U2048 = U2048::from(2);
+        assert_eq!(commit(accumulator, &[0, 1], &[4u8]), Err(VcError::LengthMismatch));
+        assert_eq!(commit(accumulator, &[0], &[4u8, 7]), Err(VcError::LengthMismatch));
+        assert_eq!(commit(accumulator, &[], &[4u8]), Err(VcError::LengthMismatch));
+        assert_eq!(update(accumulator, accumulator, U2048::from(1), &[0], &[4u8, 7]), Err(VcError::LengthMismatch));
+        assert_eq!(update(accumulator, accumulator, U2048::from(1), &[0, 1], &[4u8]), Err(VcError::LengthMismatch));
+        assert_eq!(open_at_key(accumulator, U2048::from(1), usize::max_value(), 4u8), Err(VcError::KeyOutOfRange));
+    }
+
+    #[test]
+    fn test_validate_indices() {
+        assert_eq!(validate_indices(&(0..24).collect::<Vec<usize>>()), Ok(()));
+        assert_eq!(validate_indices(&[5, 3, 9, 0]), Ok(()));
+
+        // Two 16 bit values placed with a stride of 8 bits overlap on [8, 16)
+        let mut indices: Vec<usize> = (0..16).collect();
+        indices.append(&mut (8..24).collect());
+        assert_eq!(validate_indices(&indices), Err(VcError::OverlappingIndices));
+
+        assert_eq!(convert_key_value(&[2, 2], &[4u8, 7]), Err(VcError::OverlappingIndices));
+        assert_eq!(convert_key_bytes(&[1, 0, 1], &[vec![1], vec![2], vec![3]], 2), Err(VcError::OverlappingIndices));
+    }
+
+    #[test]
+    fn test_commit_duplicate_keys() {
+        let accumulator: U2048 = U2048::from(2);
+        assert_eq!(commit(accumulator, &[0, 0], &[4u8, 9]), Err(VcError::OverlappingIndices));
+        assert_eq!(commit(accumulator, &[0, 0], &[4u8, 4]), Err(VcError::OverlappingIndices));
+        assert_eq!(commit(accumulator, &[1, 0, 2], &[4u8, 9, 7]).is_ok(), true);
+    }
+
+    #[test]
+    fn test_vc_open_and_verify() {
+        let accumulator: U2048 = U2048::from(2);
+        let keys = vec![0, 1];
+        let values: Vec<u8> = vec![5, 7];
+        let (new_accumulator, product) = commit(accumulator, &keys, &values).unwrap();
+
+        let (pi_i, pi_e) = open_at_key(accumulator, product, 1, 7u8).unwrap();
+
+        assert_eq!(verify_at_key(accumulator, new_accumulator, 1, 7u8, pi_i, pi_e).is_ok(), true);
+        assert_eq!(verify_at_key(accumulator, new_accumulator, 0, 7u8, pi_i, pi_e).is_ok(), false);
+        assert_eq!(verify_at_key(accumulator, new_accumulator, 1, 5u8, pi_i, pi_e).is_ok(), false);
+    }
+
+    #[test]
+    fn test_open_at_key_ct() {
+        let accumulator: U2048 = U2048::from(2);
+        let keys = vec![0, 1, 2];
+        let values: Vec<u8> = vec![5, 7, 0];
+        let (state, product) = commit(accumulator, &keys, &values).unwrap();
+
+        for (&key, &value) in keys.iter().zip(values.iter()) {
+            let opening = open_at_key_ct(accumulator, product, key, value);
+            assert_eq!(opening, open_at_key(accumulator, product, key, value));
+            let (pi_i, pi_e) = opening.unwrap();
+            assert_eq!(verify_at_key(accumulator, state, key, value, pi_i, pi_e), Ok(()));
+        }
+
+        assert_eq!(open_at_key_ct(accumulator, product, 1, 5u8), open_at_key(accumulator, product, 1, 5u8));
+        assert_eq!(open_at_key_ct(accumulator, product, 2, 8u8), Err(VcError::NotCommitted));
+        assert_eq!(open_at_key_ct(accumulator, product, usize::max_value(), 1u8), Err(VcError::KeyOutOfRange));
+    }
+
+    #[test]
+    fn test_from_binary() {
+        for elem in 0..=255u8 {
+            assert_eq!(from_binary::<u8>(&to_binary(elem)), elem);
+        }
+        assert_eq!(from_binary::<u8>(&[false, false, false, false, false, true, true, false]), 6);
+        assert_eq!(from_binary::<u32>(&to_binary(1_000_000u32)), 1_000_000);
+    }
+
+    #[test]
+    fn test_verify_errors() {
+        let accumulator: U2048 = U2048::from(2);
+        let keys = vec![0, 1];
+        let values: Vec<u8> = vec![5, 7];
+        let (new_accumulator, product) = commit(accumulator, &keys, &values).unwrap();
+        let (pi_i, pi_e) = open_at_key(accumulator, product, 1, 7u8).unwrap();
+
+        assert_eq!(verify_at_key(accumulator, new_accumulator, 1, 7u8, pi_i, pi_e), Ok(()));
+        assert_eq!(verify_at_key(accumulator, new_accumulator, 1, 7u8, pi_e, pi_i), Err(VerifyError::BadWitness));
+        assert_eq!(verify_at_key(accumulator, new_accumulator, usize::max_value(), 7u8, pi_i, pi_e), Err(VerifyError::KeyOutOfRange));
+        assert_eq!(verify_at_key(accumulator, new_accumulator, 1, 5u8, pi_i, pi_e), Err(VerifyError::ValueMismatch));
+        assert_eq!(verify_at_key(U2048::from(3), new_accumulator, 1, 7u8, pi_i, pi_e), Err(VerifyError::AccumulatorMismatch));
+        assert_eq!(verify_at_keys(accumulator, new_accumulator, &keys, &[7u8], pi_i, pi_e), Err(VerifyError::LengthMismatch));
+    }
+
+    #[test]
+    fn test_verify_at_key_noalloc() {
+        let accumulator: U2048 = U2048::from(2);
+        let (keys, values) = ([0, 1, 2], [4u16, 0x1234, 0xFF00]);
+        let (state, product) = commit(accumulator, &keys, &values).unwrap();
+        for (&key, &value) in keys.iter().zip(values.iter()) {
+            let (pi_i, pi_e) = open_at_key(accumulator, product, key, value).unwrap();
+            for &(old_state, claimed, pi_i, pi_e) in [(accumulator, value, pi_i, pi_e), (accumulator, value ^ 1, pi_i, pi_e),
+                                                       (accumulator, value, pi_e, pi_i), (U2048::from(3), value, pi_i, pi_e)].iter() {
+                assert_eq!(verify_at_key_noalloc(old_state, state, key, claimed, pi_i, pi_e),
+                           verify_at_key(old_state, state, key, claimed, pi_i, pi_e));
+            }
+            assert_eq!(verify_at_key_noalloc(accumulator, state, key, value, pi_i, pi_e), Ok(()));
+        }
+        let (pi_i, pi_e) = open_at_key(accumulator, product, 0, 4u16).unwrap();
+        assert_eq!(verify_at_key_noalloc(accumulator, state, usize::max_value(), 4u16, pi_i, pi_e), Err(VerifyError::KeyOutOfRange));
+    }
+
+    #[test]
+    fn test_verify_membership() {
+        let accumulator: U2048 = U2048::from(2);
+        let (state, product) = commit(accumulator, &[0, 1], &[5u8, 7]).unwrap();
+        let (pi_i, pi_e) = open_at_key(accumulator, product, 1, 7u8).unwrap();
+
+        assert_eq!(verify_membership(state, 1, 7u8, pi_i), true);
+        assert_eq!(verify_membership(state, 1, 5u8, pi_i), false);
+        assert_eq!(verify_membership(state, 1, 7u8, pi_e), false);
+        assert_eq!(verify_membership(state, usize::max_value(), 7u8, pi_i), false);
+    }
+
+    #[test]
+    fn test_light_verifier() {
+        let accumulator: U2048 = U2048::from(2);
+        let (state, product) = commit(accumulator, &[0, 1], &[5u8, 7]).unwrap();
+        let (pi_i, pi_e) = open_at_key(accumulator, product, 1, 7u8).unwrap();
+
+        // The root synced from an encoded Commitment
+        let mut bytes: [u8; 256] = [0; 256];
+        bytes.copy_from_slice(&Commitment { state, product }.encode()[..256]);
+        let verifier = LightVerifier::from_bytes(&bytes);
+        assert_eq!(verifier, LightVerifier::new(state));
+        assert_eq!(verifier.root(), state);
+        assert_eq!(verifier.encode(), bytes.to_vec());
+        assert_eq!(LightVerifier::decode(&mut &bytes[..]).unwrap(), verifier);
+
+        assert_eq!(verifier.verify_inclusion(1, 7u8, pi_i, pi_e), true);
+        assert_eq!(verifier.verify_inclusion(1, 5u8, pi_i, pi_e), false);
+        assert_eq!(verifier.verify_inclusion(1, 7u8, pi_i, pi_i), false);
+    }
+
+    #[test]
+    fn test_membership_witness() {
+        let accumulator: U2048 = U2048::from(2);
+        let (state, product) = commit(accumulator, &[0, 1], &[4u8, 7]).unwrap();
+
+        let witness = MembershipWitness::from_key_value(accumulator, product, 1, 7u8).unwrap();
+        assert_eq!(witness.element, get_key_value_elem(1, 7u8).unwrap());
+        assert_eq!(witness.verify(state), true);
+        assert_eq!(witness.verify(accumulator), false);
+
+        let (pi_i, pi_e) = open_at_key(accumulator, product, 1, 7u8).unwrap();
+        assert_eq!(witness.proof, pi_i);
+        assert_eq!(MembershipWitness::new(witness.element, pi_e).verify(state), false);
+        assert_eq!(MembershipWitness::from_key_value(accumulator, product, 1, 9u8), Err(VcError::NotCommitted));
+    }
+
+    #[test]
+    fn test_verify_oversized_witness() {
+        let accumulator: U2048 = U2048::from(2);
+        let modulus = U2048::from_dec_str(MODULUS).unwrap();
+        let (state, product) = commit(accumulator, &[0, 1], &[4u8, 7]).unwrap();
+        let (pi_i, pi_e) = open_at_key(accumulator, product, 1, 7u8).unwrap();
+        let (mem_wit, (a, sign_a, B)) = match (pi_i, pi_e) {
+            (Witness::MemWit(mem_wit), Witness::NonMemWit(non_mem_wit)) => (mem_wit, non_mem_wit),
+            _ => panic!(),
+        };
+
+        // mod_exp silently reduces the oversized witness, but verification rejects it
+        let oversized = Witness::MemWit(mem_wit + modulus);
+        assert_eq!(witnesses::verify_mem_wit(state, mem_wit + modulus, get_key_value_elem(1, 7u8).unwrap()), true);
+        assert_eq!(verify_at_key(accumulator, state, 1, 7u8, oversized, pi_e), Err(VerifyError::BadWitness));
+        assert_eq!(verify_at_key(accumulator, state, 1, 7u8, pi_i, Witness::NonMemWit((a, sign_a, B + modulus))), Err(VerifyError::BadWitness));
+        assert_eq!(verify_at_key(accumulator, state, 1, 7u8, pi_i, pi_e), Ok(()));
+    }
+
+    #[test]
+    fn test_prove_absent() {
+        let accumulator: U2048 = U2048::from(2);
+        let keys = vec![0, 1];
+        let values: Vec<u8> = vec![4, 7];
+        let (new_accumulator, product) = commit(accumulator, &keys, &values).unwrap();
+
+        let proof = prove_absent::<u8>(accumulator, product, 3).unwrap();
+        assert_eq!(verify_absent::<u8>(accumulator, new_accumulator, 3, proof), true);
+        assert_eq!(verify_absent::<u8>(accumulator, new_accumulator, 1, proof), false);
+        assert_eq!(verify_absent::<u8>(accumulator, new_accumulator, 2, proof), false);
+
+        // Committed keys cannot be proven absent
+        assert_eq!(prove_absent::<u8>(accumulator, product, 1), Err(VcError::KeyPresent));
+        assert_eq!(prove_absent::<u8>(accumulator, product, usize::max_value()), Err(VcError::KeyOutOfRange));
+    }
+
+    #[test]
+    fn test_batch_verify_memberships() {
+        let accumulator: U2048 = U2048::from(2);
+        let keys: Vec<usize> = (0..20).collect();
+        let values: Vec<u8> = (0..20).map(|key| (key * 37 + 1) as u8).collect();
+        let (state, product) = commit(accumulator, &keys, &values).unwrap();
+
+        let mut items: Vec<(usize, u8, Witness)> = open_each(accumulator, product, &keys, &values)
+            .unwrap()
+            .into_iter()
+            .map(|(key, pi_i, _)| (key, values[key], pi_i))
+            .collect();
+        assert_eq!(batch_verify_memberships(state, &items), true);
+
+        // Claim a value with an additional set bit for one of the keys
+        items[7].1 = values[7] | 16;
+        assert_eq!(verify_membership(state, 7, items[7].1, items[7].2), false);
+        assert_eq!(batch_verify_memberships(state, &items), false);
+
+        items[7].1 = values[7];
+        items[7].2 = Witness::NonMemWit((U2048::from(1), false, U2048::from(1)));
+        assert_eq!(batch_verify_memberships(state, &items), false);
+        assert_eq!(batch_verify_memberships::<u8>(state, &[]), true);
+    }
+
+    #[test]
+    fn test_open_bit() {
+        let accumulator: U2048 = U2048::from(2);
+        let (state, product) = commit(accumulator, &[0, 1], &[4u8, 7]).unwrap();
+
+        // 7 is stored as [0, 0, 0, 0, 0, 1, 1, 1]
+        let proof = open_bit::<u8>(accumulator, product, 1, 5).unwrap();
+        assert_eq!(verify_bit::<u8>(state, 1, 5, proof), true);
+        assert_eq!(verify_bit::<u8>(state, 0, 5, proof), false);
+
+        assert_eq!(open_bit::<u8>(accumulator, product, 1, 4), Err(VcError::NotCommitted));
+        assert_eq!(open_bit::<u8>(accumulator, product, 1, 8), Err(VcError::OutOfRange));
+        assert_eq!(open_bit::<u8>(accumulator, product, usize::max_value(), 0), Err(VcError::KeyOutOfRange));
+        assert_eq!(verify_bit::<u8>(state, 1, 8, proof), false);
+    }
+
+    #[test]
+    fn test_sum() {
+        let accumulator: U2048 = U2048::from(2);
+        let keys = [2, 5, 9];
+        let values: Vec<u8> = vec![10, 30, 2];
+        let (state, product) = commit(accumulator, &keys, &values).unwrap();
+
+        let proof = prove_sum(accumulator, product, &keys, &values).unwrap();
+        assert_eq!(verify_sum(accumulator, state, &keys, 42, &proof), true);
+        assert_eq!(verify_sum(accumulator, state, &keys, 41, &proof), false);
+        assert_eq!(verify_sum(accumulator, state, &keys, 43, &proof), false);
+
+        // Values that add up to the same total but differ from the committed ones are rejected
+        let forged = SumProof { values: vec![11u8, 29, 2], ..proof.clone() };
+        assert_eq!(verify_sum(accumulator, state, &keys, 42, &forged), false);
+        assert_eq!(verify_sum(accumulator, state, &[2, 5, 8], 42, &proof), false);
+
+        // Values that are not committed cannot be proven
+        assert_eq!(prove_sum(accumulator, product, &keys, &[11u8, 29, 2]), Err(VcError::NotCommitted));
+    }
+
+    #[test]
+    fn test_is_committed() {
+        let accumulator: U2048 = U2048::from(2);
+        let (_, product) = commit(accumulator, &[0, 1], &[5u8, 0]).unwrap();
+        assert_eq!(is_committed::<u8>(product, 0), Ok(true));
+
+        // A key holding zero cannot be told apart from a key that was never written
+        assert_eq!(is_committed::<u8>(product, 1), Ok(false));
+        assert_eq!(is_committed::<u8>(product, 2), Ok(false));
+        assert_eq!(get_key_value_elem(1, 0u8), Ok(U2048::from(1)));
+
+        assert_eq!(is_committed::<u8>(product, usize::max_value()), Err(VcError::KeyOutOfRange));
+    }
+
+    #[test]
+    fn test_range() {
+        let accumulator: U2048 = U2048::from(2);
+        let (state, product) = commit(accumulator, &[0, 1], &[10u8, 200]).unwrap();
+
+        let proof = prove_range(accumulator, product, 0, 10u8, 4).unwrap();
+        assert_eq!(verify_range::<u8>(accumulator, state, 0, 4, proof), true);
+        assert_eq!(verify_range::<u8>(accumulator, state, 0, 5, proof), false);
+        assert_eq!(verify_range::<u8>(accumulator, state, 2, 4, proof), false);
+
+        // 200 does not fit in 4 bits, even when lying about the value
+        assert_eq!(prove_range(accumulator, product, 1, 200u8, 4), Err(VcError::OutOfRange));
+        assert_eq!(prove_range(accumulator, product, 1, 8u8, 4), Err(VcError::OutOfRange));
+        let proof = prove_range(accumulator, product, 1, 200u8, 8).unwrap();
+        assert_eq!(verify_range::<u8>(accumulator, state, 1, 8, proof), true);
+    }
+
+    #[test]
+    fn test_non_membership_at_key() {
+        let accumulator: U2048 = U2048::from(2);
+        let keys = vec![0, 1];
+        let values: Vec<u8> = vec![4, 9];
+        let (state, product) = commit(accumulator, &keys, &values).unwrap();
+
+        let proof = prove_non_membership_at_key(accumulator, product, 1, 6u8).unwrap();
+        assert_eq!(verify_non_membership_at_key(accumulator, state, 1, 6u8, proof), true);
+        assert_eq!(verify_non_membership_at_key(accumulator, state, 1, 9u8, proof), false);
+        assert_eq!(verify_non_membership_at_key(accumulator, state, 0, 6u8, proof), false);
 
-/// Update the values for a set of keys. Assumes key-value pairs are valid.
-pub fn update(accumulator: U2048, old_state: U2048, agg: U2048, keys: &[usize], values: &[ValueType]) -> U2048 {
-    let (binary_vec, indices) = convert_key_value(keys, values);
-    return binary::update(accumulator, old_state, agg, &binary_vec, &indices);
-}
+        // Uncommitted keys work as well
+        let proof = prove_non_membership_at_key(accumulator, product, 3, 1u8).unwrap();
+        assert_eq!(verify_non_membership_at_key(accumulator, state, 3, 1u8, proof), true);
 
-/// Converts key-value pairs into a binary representation of the values along with corresponding
-/// indices.
-pub fn convert_key_value(keys: &[usize], values: &[ValueType]) -> (Vec<bool>, Vec<usize>) {
-    let mut binary_vec: Vec<bool> = [].to_vec();
-    let mut indices: Vec<usize> = [].to_vec();
-    for (i, &value) in values.iter().enumerate() {
-        let mut value_vec = to_binary(value);
-        let offset = core::mem::size_of::<ValueType>()*8;
-        let mut index_vec = (keys[i]*offset..keys[i]*offset+offset).collect();
-        binary_vec.append(&mut value_vec);
-        indices.append(&mut index_vec);
+        // Proof generation fails if the value is committed
+        assert_eq!(prove_non_membership_at_key(accumulator, product, 1, 9u8), Err(VcError::ValuePresent));
+        assert_eq!(prove_non_membership_at_key(accumulator, product, 1, 8u8), Err(VcError::ValuePresent));
+        assert_eq!(prove_non_membership_at_key(accumulator, product, 1, 0u8), Err(VcError::ValuePresent));
     }
-    return (binary_vec, indices);
-}
 
-/// Converts an element to a binary representation.
-pub fn to_binary(elem: ValueType) -> Vec<bool> {
-    let byte_vec = elem.to_le_bytes().to_vec();
-    let bv = BitVec::from_bytes(&byte_vec);
-    return bv.iter().collect::<Vec<bool>>();
-}
+    #[test]
+    fn test_update_single() {
+        let accumulator: U2048 = U2048::from(2);
+        let keys = vec![0, 1];
+        let values: Vec<u8> = vec![4, 0x0F];
+        let (state, product) = commit(accumulator, &keys, &values).unwrap();
 
-/// Quick helper function that gets the product of the accumulated elements for a given
-/// key-value pair.
-pub fn get_key_value_elem(key: usize, value: ValueType) -> U2048 {
-    let (binary_vec, indices) = convert_key_value(&[key], &[value]);
-    let (elem, _) = binary::get_bit_elems(&binary_vec, &indices);
-    return elem;
-}
+        // Agrees with a full update
+        let new_state = update_single(state, accumulator, product, 1, 0x0Fu8, 0xF0u8).unwrap();
+        assert_eq!(new_state, update(state, accumulator, product, &[1], &[0xF0u8]).unwrap());
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        // Partial transitions match a fresh commitment to the new values
+        let new_state = update_single(state, accumulator, product, 0, 4u8, 6u8).unwrap();
+        let (expected, _) = commit(accumulator, &keys, &[6u8, 0x0F]).unwrap();
+        assert_eq!(new_state, expected);
+
+        // Unchanged values leave the state untouched
+        assert_eq!(update_single(state, accumulator, product, 0, 4u8, 4u8).unwrap(), state);
+
+        assert_eq!(update_single(state, accumulator, product, 0, 5u8, 6u8), Err(VcError::NotCommitted));
+    }
 
     #[test]
-    fn test_to_binary() {
-        let elem: ValueType = 6;
-        let bv = to_binary(elem);
-        assert_eq!(bv, vec![false, false, false, false, false, true, true, false]);
+    fn test_remove_key() {
+        let accumulator: U2048 = U2048::from(2);
+        let keys = vec![0, 1];
+        let values: Vec<u8> = vec![4, 8];
+        let (state, product) = commit(accumulator, &keys, &values).unwrap();
+        let (pi_i, pi_e) = open_at_key(accumulator, product, 1, 8u8).unwrap();
+
+        let new_state = remove_key(state, accumulator, product, 1, 8u8).unwrap();
+        let new_product = product / get_key_value_elem(1, 8u8).unwrap();
+
+        // The removed key no longer verifies while the remaining key does
+        assert_eq!(verify_at_key(accumulator, new_state, 1, 8u8, pi_i, pi_e).is_ok(), false);
+        let (pi_i, pi_e) = open_at_key(accumulator, new_product, 0, 4u8).unwrap();
+        assert_eq!(verify_at_key(accumulator, new_state, 0, 4u8, pi_i, pi_e).is_ok(), true);
+
+        // Cannot remove a pair that was never committed
+        assert_eq!(remove_key(state, accumulator, product, 1, 6u8), Err(VcError::NotCommitted));
     }
 
     #[test]
-    fn test_commit() {
+    fn test_delete() {
+        let accumulator: U2048 = U2048::from(2);
+        let keys = vec![0, 1];
+        let values: Vec<u8> = vec![4, 8];
+        let (state, product) = commit(accumulator, &keys, &values).unwrap();
+        let (pi_i, pi_e) = open_at_key(accumulator, product, 1, 8u8).unwrap();
+
+        // Reject witnesses that do not match the key-value pair
+        assert_eq!(delete(state, pi_i, 1, 6u8), Err(VcError::BadWitness));
+        assert_eq!(delete(state, pi_e, 1, 8u8), Err(VcError::BadWitness));
+
+        let new_state = delete(state, pi_i, 1, 8u8).unwrap();
+        assert_eq!(new_state, remove_key(state, accumulator, product, 1, 8u8).unwrap());
+
+        // The deleted key no longer verifies while the remaining key does
+        assert_eq!(verify_at_key(accumulator, new_state, 1, 8u8, pi_i, pi_e).is_ok(), false);
+        let new_product = product / get_key_value_elem(1, 8u8).unwrap();
+        let (pi_i, pi_e) = open_at_key(accumulator, new_product, 0, 4u8).unwrap();
+        assert_eq!(verify_at_key(accumulator, new_state, 0, 4u8, pi_i, pi_e).is_ok(), true);
+    }
+
+    #[test]
+    fn test_update_witness() {
+        let accumulator: U2048 = U2048::from(2);
+        let (state, product) = commit(accumulator, &[0], &[4u8]).unwrap();
+        let (pi_i, pi_e) = open_at_key(accumulator, product, 0, 4u8).unwrap();
+
+        // Someone else commits key 1
+        let (new_state, _) = commit(state, &[1], &[8u8]).unwrap();
+        assert_eq!(verify_at_key(accumulator, new_state, 0, 4u8, pi_i, pi_e).is_ok(), false);
+
+        let (pi_i, pi_e) = update_witness(state, new_state, 0, 4u8, pi_i, pi_e, &[(1, 8u8)], &[]).unwrap();
+        assert_eq!(verify_at_key(accumulator, new_state, 0, 4u8, pi_i, pi_e).is_ok(), true);
+
+        // Key 1 is then removed again
+        let (pi_i, pi_e) = update_witness(new_state, state, 0, 4u8, pi_i, pi_e, &[], &[(1, 8u8)]).unwrap();
+        assert_eq!(verify_at_key(accumulator, state, 0, 4u8, pi_i, pi_e).is_ok(), true);
+
+        // The witnessed key cannot be removed
+        assert_eq!(update_witness(state, accumulator, 0, 4u8, pi_i, pi_e, &[], &[(0, 4u8)]), Err(VcError::BadWitness));
+    }
+
+    #[test]
+    fn test_refresh_witness_after_commit() {
+        let accumulator: U2048 = U2048::from(2);
+        let (state, product) = commit(accumulator, &[0], &[4u8]).unwrap();
+        let (pi_i, pi_e) = open_at_key(accumulator, product, 0, 4u8).unwrap();
+
+        let (new_state, _) = commit(state, &[1], &[9u8]).unwrap();
+        let (pi_i, pi_e) = refresh_witness_after_commit(state, new_state, 0, 4u8, pi_i, pi_e, &[(1, 9u8)]).unwrap();
+        assert_eq!(verify_at_key(accumulator, new_state, 0, 4u8, pi_i, pi_e), Ok(()));
+        assert_eq!(pi_i, Witness::MemWit(witnesses::update_mem_wit_on_add(
+            witnesses::mem_wit_create(accumulator, product, get_key_value_elem(0, 4u8).unwrap()).unwrap(),
+            get_key_value_elem(1, 9u8).unwrap(), U2048::from_dec_str(MODULUS).unwrap())));
+        assert_eq!(refresh_witness_after_commit(state, new_state, 0, 4u8, pi_e, pi_i, &[(1, 9u8)]), Err(VcError::BadWitness));
+    }
+
+    #[test]
+    fn test_refresh_witness_after_delete() {
+        let accumulator: U2048 = U2048::from(2);
+        let (state, product) = commit(accumulator, &[0, 1, 2], &[4u8, 7, 9]).unwrap();
+        let (pi_i, pi_e) = open_at_key(accumulator, product, 0, 4u8).unwrap();
+
+        let new_state = remove_key(state, accumulator, product, 1, 7u8).unwrap();
+        assert_eq!(new_state, commit(accumulator, &[0, 2], &[4u8, 9]).unwrap().0);
+        let (new_pi_i, new_pi_e) = refresh_witness_after_delete(state, new_state, 0, 4u8, pi_i, pi_e, &[(1, 7u8)]).unwrap();
+        assert_eq!(verify_at_key(accumulator, new_state, 0, 4u8, new_pi_i, new_pi_e), Ok(()));
+
+        // The opened pair itself cannot be removed
+        let new_state = remove_key(state, accumulator, product, 0, 4u8).unwrap();
+        assert_eq!(refresh_witness_after_delete(state, new_state, 0, 4u8, pi_i, pi_e, &[(0, 4u8)]), Err(VcError::BadWitness));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_commit_with_cache() {
+        let accumulator: U2048 = U2048::from(2);
+        let mut cache = binary::PrimeCache::new();
+        let cold = commit_with_cache(&mut cache, accumulator, &[0, 1], &[4u8, 9]).unwrap();
+        assert_eq!(cold, commit(accumulator, &[0, 1], &[4u8, 9]).unwrap());
+        assert_eq!(commit_with_cache(&mut cache, accumulator, &[1, 0], &[9u8, 4]).unwrap(), cold);
+        assert_eq!(commit_with_cache(&mut cache, accumulator, &[0, 0], &[4u8, 9]), Err(VcError::OverlappingIndices));
+    }
+
+    #[test]
+    fn test_commit_with_proof() {
+        let accumulator: U2048 = U2048::from(2);
+        let (state, product, proof) = commit_with_proof(accumulator, &[0, 1], &[4u8, 9]).unwrap();
+        assert_eq!((state, product), commit(accumulator, &[0, 1], &[4u8, 9]).unwrap());
+        assert_eq!(verify_commit(accumulator, state, product, proof), true);
+
+        // Tampered state or product
+        assert_eq!(verify_commit(accumulator, state + U2048::from(1), product, proof), false);
+        assert_eq!(verify_commit(accumulator, state, product + U2048::from(1), proof), false);
+    }
+
+    #[test]
+    fn test_with_modulus() {
+        let accumulator: U2048 = U2048::from(2);
+        let modulus = U2048::from(3233);
+
+        // Matches the default group when using MODULUS
+        assert_eq!(commit_with_modulus(accumulator, &[0, 1], &[4u8, 9], U2048::from_dec_str(MODULUS).unwrap()),
+                   commit(accumulator, &[0, 1], &[4u8, 9]));
+
+        let (state, product) = commit_with_modulus(accumulator, &[0, 1], &[4u8, 9], modulus).unwrap();
+        let (pi_i, pi_e) = open_at_key_with_modulus(accumulator, product, 0, 4u8, modulus).unwrap();
+        assert_eq!(verify_at_key_with_modulus(accumulator, state, 0, 4u8, pi_i, pi_e, modulus), Ok(()));
+        assert_eq!(verify_at_key_with_modulus(accumulator, state, 0, 6u8, pi_i, pi_e, modulus).is_ok(), false);
+
+        let (pi_i, pi_e) = open_at_key_with_modulus(accumulator, product, 1, 9u8, modulus).unwrap();
+        assert_eq!(verify_at_key_with_modulus(accumulator, state, 1, 9u8, pi_i, pi_e, modulus), Ok(()));
+        assert_eq!(verify_at_key_with_modulus(accumulator, state, 1, 8u8, pi_i, pi_e, modulus).is_ok(), false);
+        assert_eq!(open_at_key_with_modulus(accumulator, product, 1, 6u8, modulus), Err(VcError::NotCommitted));
+        assert_eq!(open_at_key_with_modulus(accumulator, product, 1, 8u8, modulus), Err(VcError::ValuePresent));
+    }
+
+    #[test]
+    fn test_4096() {
+        // 96 set bits give a product of roughly 3000 bits, which would overflow U2048
+        let accumulator = wide::U4096::from(2);
+        let values: Vec<u64> = vec![0xFFFF_FFFF_FFFF_0000, 0x0000_FFFF_FFFF_FFFF];
+        let (state, product) = commit_4096(accumulator, &[0, 1], &values).unwrap();
+        assert_eq!(product.bits() > 2048, true);
+
+        let (pi_i, pi_e) = open_at_key_4096(accumulator, product, 0, values[0]).unwrap();
+        assert_eq!(verify_at_key_4096(accumulator, state, 0, values[0], pi_i, pi_e), Ok(()));
+        assert_eq!(verify_at_key_4096(accumulator, state, 0, 0xFFFF_FFFF_FFFE_0000u64, pi_i, pi_e).is_ok(), false);
+        assert_eq!(verify_at_key_4096(accumulator, state, 0, values[0], pi_e, pi_i), Err(VerifyError::BadWitness));
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_commit_instrumented() {
+        let accumulator: U2048 = U2048::from(2);
+        let ((state, product), stats) = commit_instrumented(accumulator, &[0, 1], &[4u8, 7]).unwrap();
+        assert_eq!(commit(accumulator, &[0, 1], &[4u8, 7]), Ok((state, product)));
+        assert_eq!(stats.primes_ns > 0 && stats.exp_ns > 0, true);
+        assert_eq!(commit_instrumented(accumulator, &[0, 0], &[4u8, 7]).is_err(), true);
+    }
+
+    #[test]
+    fn test_commit_in() {
+        let accumulator: U2048 = U2048::from(2);
+        assert_eq!(commit_in::<group::RsaGroup, u8>(accumulator, &[0, 1], &[4, 9]), commit(accumulator, &[0, 1], &[4u8, 9]));
+
+        let generator = group::ClassGroup::hash_to_generator(b"generator");
+        let (state, product) = commit_in::<group::ClassGroup, u8>(generator, &[0, 1], &[4, 9]).unwrap();
+        assert_eq!(state, group::ClassGroup::exp(generator, product));
+
+        let (mem_wit, non_mem_wit) = open_at_key_in::<group::ClassGroup, u8>(generator, product, 0, 4).unwrap();
+        assert_eq!(verify_at_key_in::<group::ClassGroup, u8>(generator, state, 0, 4, mem_wit, non_mem_wit), Ok(()));
+        assert_eq!(verify_at_key_in::<group::ClassGroup, u8>(generator, state, 0, 6, mem_wit, non_mem_wit).is_ok(), false);
+        assert_eq!(open_at_key_in::<group::ClassGroup, u8>(generator, product, 0, 6).is_err(), true);
+    }
+
+    #[test]
+    fn test_batch_update() {
+        let accumulator: U2048 = U2048::from(2);
+        let (state, product) = commit(accumulator, &[0, 1, 2], &[4u8, 7, 9]).unwrap();
+        let updates = [(0, 4u8, 5u8), (1, 7, 2), (2, 9, 9)];
+
+        // Apply the updates one at a time, tracking the product of the accumulated elements
+        let mut sequential = state;
+        let mut agg = product;
+        for &(key, old_value, new_value) in updates.iter() {
+            sequential = update_single(sequential, accumulator, agg, key, old_value, new_value).unwrap();
+            agg = agg / get_key_value_elem(key, old_value).unwrap() * get_key_value_elem(key, new_value).unwrap();
+        }
+
+        let batched = batch_update(accumulator, product, &updates).unwrap();
+        assert_eq!(batched, sequential);
+        assert_eq!(batched, commit(accumulator, &[0, 1, 2], &[5u8, 2, 9]).unwrap().0);
+
+        assert_eq!(batch_update(accumulator, product, &[(0, 4u8, 5u8), (0, 5, 6)]), Err(VcError::OverlappingIndices));
+        assert_eq!(batch_update(accumulator, product, &[(0, 8u8, 0u8)]), Err(VcError::NotCommitted));
+    }
+
+    #[test]
+    fn test_verify_transition() {
+        let accumulator: U2048 = U2048::from(2);
+        let (_, product) = commit(accumulator, &[0, 1, 2], &[4u8, 7, 9]).unwrap();
+        let (new_state, _) = commit(accumulator, &[0, 1, 2], &[5u8, 7, 9]).unwrap();
+        assert_eq!(verify_transition(accumulator, new_state, product, &[0], &[4u8], &[5u8]), true);
+
+        // An extra unauthorized change to key 2
+        let (tampered_state, _) = commit(accumulator, &[0, 1, 2], &[5u8, 7, 8]).unwrap();
+        assert_eq!(verify_transition(accumulator, tampered_state, product, &[0], &[4u8], &[5u8]), false);
+        assert_eq!(verify_transition(accumulator, tampered_state, product, &[0, 2], &[4u8, 9], &[5u8, 8]), true);
+
+        // The old values must match the commitment
+        assert_eq!(verify_transition(accumulator, new_state, product, &[0], &[0u8], &[5u8]), false);
+        assert_eq!(verify_transition(accumulator, new_state, product, &[0], &[4u8], &[]), false);
+    }
+
+    #[test]
+    fn test_commitment_builder() {
+        let accumulator: U2048 = U2048::from(2);
+        let mut builder = CommitmentBuilder::new(accumulator);
+        for &(key, value) in [(2, 9u8), (0, 4), (1, 7)].iter() {
+            builder.add(key, value).unwrap();
+        }
+        assert_eq!(builder.finalize(), commit(accumulator, &[0, 1, 2], &[4u8, 7, 9]));
+        assert_eq!(CommitmentBuilder::<u8>::new(accumulator).finalize(), Ok((accumulator, U2048::from(1))));
+
+        let mut builder = CommitmentBuilder::new(accumulator);
+        builder.add(0, 4u8).unwrap();
+        builder.add(0, 9u8).unwrap();
+        assert_eq!(builder.finalize(), Err(VcError::OverlappingIndices));
+        assert_eq!(CommitmentBuilder::<u8>::new(accumulator).add(usize::max_value(), 4), Err(VcError::KeyOutOfRange));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_state_tree() {
+        let accumulator: U2048 = U2048::from(2);
+        let mut tree = StateTree::new(accumulator);
+        let mut seed = runtime_io::blake2_256(b"state_tree");
+        for _ in 0..100 {
+            let (key, value) = (seed[0] as usize % 6, seed[1]);
+            assert_eq!(tree.set(key, value), Ok(tree.root()));
+            assert_eq!(tree.get(key), Some(value));
+            seed = runtime_io::blake2_256(&seed);
+        }
+
+        let mut keys: Vec<usize> = (0..6).filter(|key| tree.get(*key).is_some()).collect();
+        keys.sort();
+        let values: Vec<u8> = keys.iter().map(|key| tree.get(*key).unwrap()).collect();
+        assert_eq!(commit(accumulator, &keys, &values), Ok((tree.root(), tree.product())));
+        assert_eq!(tree.get(6), None);
+    }
+
+    #[test]
+    fn test_open_each() {
+        let accumulator: U2048 = U2048::from(2);
+        let keys = vec![0, 1, 2];
+        let values: Vec<u8> = vec![4, 7, 9];
+        let (state, product) = commit(accumulator, &keys, &values).unwrap();
+
+        let openings = open_each(accumulator, product, &keys, &values).unwrap();
+        assert_eq!(openings.len(), 3);
+        for (i, (key, pi_i, pi_e)) in openings.into_iter().enumerate() {
+            assert_eq!(key, keys[i]);
+            assert_eq!(verify_at_key(accumulator, state, key, values[i], pi_i, pi_e), Ok(()));
+            assert_eq!(verify_at_key(accumulator, state, (key + 1) % 3, values[i], pi_i, pi_e).is_ok(), false);
+        }
+        assert_eq!(open_each(accumulator, product, &keys, &values[..2]), Err(VcError::LengthMismatch));
+        assert_eq!(open_each(accumulator, product, &[3], &[4u8]), Err(VcError::NotCommitted));
+    }
+
+    #[test]
+    fn test_open_all() {
         let accumulator: U2048 = U2048::from(2);
+        let keys = vec![0, 1, 2];
+        let values: Vec<u8> = vec![4, 7, 9];
+        let (state, product) = commit(accumulator, &keys, &values).unwrap();
+
+        let openings = open_all(accumulator, &keys, &values).unwrap();
+        assert_eq!(openings.len(), 3);
+        for ((key, value), (pi_i, pi_e)) in keys.iter().zip(values.iter()).zip(openings.into_iter()) {
+            assert_eq!(verify_at_key(accumulator, state, *key, *value, pi_i, pi_e).is_ok(), true);
+            assert_eq!((pi_i, pi_e), open_at_key(accumulator, product, *key, *value).unwrap());
+        }
+
+        assert_eq!(open_all::<u8>(accumulator, &[], &[]).unwrap().len(), 0);
+        assert_eq!(open_all(accumulator, &[0, 0], &[4u8, 9]), Err(VcError::OverlappingIndices));
+    }
+
+    #[test]
+    fn test_aggregate_witnesses() {
+        let accumulator: U2048 = U2048::from(2);
+        let keys = vec![0, 1, 2];
+        let values: Vec<u8> = vec![4, 7, 9];
+        let (state, product) = commit(accumulator, &keys, &values).unwrap();
+
+        let openings: Vec<(Witness, Witness)> = keys.iter().zip(values.iter())
+            .map(|(key, value)| open_at_key(accumulator, product, *key, *value).unwrap())
+            .collect();
+        let (pi_i, pi_e) = aggregate_witnesses(state, &keys, &values, &openings).unwrap();
+        assert_eq!(verify_at_keys(accumulator, state, &keys, &values, pi_i, pi_e).is_ok(), true);
+        assert_eq!(verify_at_keys(accumulator, state, &keys, &[4u8, 7, 8], pi_i, pi_e).is_ok(), false);
+
+        // Duplicate keys and mismatched lengths are rejected
+        assert_eq!(aggregate_witnesses(accumulator, &[0, 0], &[4u8, 4], &openings[..2]), Err(VcError::OverlappingIndices));
+        assert_eq!(aggregate_witnesses(accumulator, &keys, &values, &openings[..2]), Err(VcError::LengthMismatch));
+    }
+
+    #[test]
+    fn test_genesis() {
+        let allocation: Vec<(usize, u8)> = vec![(0, 100), (3, 42), (7, 255)];
+        let (root, product) = genesis(&allocation).unwrap();
+        assert_eq!((root, product), commit(U2048::from(2), &[0, 3, 7], &[100u8, 42, 255]).unwrap());
+
+        for &(key, balance) in allocation.iter() {
+            let (pi_i, pi_e) = open_at_key(U2048::from(2), product, key, balance).unwrap();
+            assert_eq!(verify_at_key(U2048::from(2), root, key, balance, pi_i, pi_e), Ok(()));
+        }
+        let (pi_i, pi_e) = open_at_key(U2048::from(2), product, 3, 42u8).unwrap();
+        assert_eq!(verify_at_key(U2048::from(2), root, 3, 41u8, pi_i, pi_e).is_ok(), false);
+
+        assert_eq!(genesis(&[(0, 100u8), (3, 42), (0, 1)]), Err(VcError::OverlappingIndices));
+        assert_eq!(genesis::<u8>(&[]), Ok((U2048::from(2), U2048::from(1))));
+    }
+
+    #[test]
+    fn test_commit_with_generator() {
         let keys = [0, 1];
-        let values = vec![4, 7];
+        let values: Vec<u8> = vec![4, 7];
+        let (root_3, product) = commit_with_generator(U2048::from(3), &keys, &values).unwrap();
+        let (root_2, _) = commit_with_generator(U2048::from(2), &keys, &values).unwrap();
+        assert_ne!(root_3, root_2);
+        assert_eq!((root_2, product), commit(U2048::from(2), &keys, &values).unwrap());
 
-        let (new_accumulator, _) = commit(accumulator, &keys, &values);
+        let (pi_i, pi_e) = open_at_key(U2048::from(3), product, 1, 7u8).unwrap();
+        assert_eq!(verify_at_key(U2048::from(3), root_3, 1, 7u8, pi_i, pi_e), Ok(()));
+        assert_eq!(verify_at_key(U2048::from(2), root_2, 1, 7u8, pi_i, pi_e).is_ok(), false);
+        assert_eq!(genesis_with_generator(U2048::from(3), &[(0, 4u8), (1, 7)]), Ok((root_3, product)));
 
-        // Manual check
-        let check_product = subroutines::hash_to_prime(&(5 as usize).to_le_bytes())
-            * subroutines::hash_to_prime(&(13 as usize).to_le_bytes())
-            * subroutines::hash_to_prime(&(14 as usize).to_le_bytes())
-            * subroutines::hash_to_prime(&(15 as usize).to_le_bytes());
+        let modulus = U2048::from_dec_str(MODULUS).unwrap();
+        for &generator in [U2048::from(0), U2048::from(1), modulus - U2048::from(1), modulus, modulus * U2048::from(2)].iter() {
+            assert_eq!(validate_generator(generator), Err(VcError::InvalidGenerator));
+            assert_eq!(commit_with_generator(generator, &keys, &values), Err(VcError::InvalidGenerator));
+        }
+    }
 
-        assert_eq!(new_accumulator, subroutines::mod_exp(U2048::from(2), U2048::from(check_product), U2048::from_dec_str(MODULUS).unwrap()));
+    #[test]
+    fn test_commit_empty() {
+        let accumulator: U2048 = U2048::from(2);
+        assert_eq!(commit::<u8>(accumulator, &[], &[]), Ok((accumulator, U2048::from(1))));
+        assert_eq!(commit::<u8>(U2048::from(20), &[], &[]), Ok((U2048::from(20), U2048::from(1))));
+        assert_eq!(commit::<u8>(accumulator, &[0], &[]), Err(VcError::LengthMismatch));
     }
 
     #[test]
-    fn test_convert() {
+    fn test_open_uncommitted_key() {
+        let accumulator: U2048 = U2048::from(2);
+        let (state, product) = commit(accumulator, &[0], &[5u8]).unwrap();
+        assert_eq!(open_at_key(accumulator, product, 1, 8u8), Err(VcError::NotCommitted));
+
+        let (pi_i, pi_e) = open_at_key(accumulator, product, 0, 5u8).unwrap();
+        assert_eq!(verify_at_key(accumulator, state, 1, 255u8, pi_i, pi_e).is_ok(), false);
+
+        // An uncommitted key holds the value 0
+        let (pi_i, pi_e) = open_at_key(accumulator, product, 1, 0u8).unwrap();
+        assert_eq!(verify_at_key(accumulator, state, 1, 0u8, pi_i, pi_e), Ok(()));
+        assert_eq!(verify_at_key(accumulator, state, 1, 8u8, pi_i, pi_e).is_ok(), false);
+    }
+
+    #[test]
+    fn test_disaggregate() {
+        let accumulator: U2048 = U2048::from(2);
+        let (state, product) = commit(accumulator, &[0, 1, 2], &[4u8, 7, 9]).unwrap();
+        let (pi_i, pi_e) = open_at_keys(accumulator, product, &[0, 1, 2], &[4u8, 7, 9]).unwrap();
+
+        // Remove key 1 from both witnesses
+        let (binary_vec, indices) = convert_key_value(&[1], &[7u8]).unwrap();
+        let (p_ones, p_zeros) = binary::get_bit_elems(&binary_vec, &indices);
+        let new_pi_i = disaggregate(pi_i, p_ones);
+        let new_pi_e = disaggregate(pi_e, p_zeros);
+        assert_eq!(verify_at_keys(accumulator, state, &[0, 2], &[4u8, 9], new_pi_i, new_pi_e), Ok(()));
+        assert_eq!(verify_at_keys(accumulator, state, &[0, 2], &[4u8, 8], new_pi_i, new_pi_e).is_ok(), false);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_verify_batch() {
+        let accumulator: U2048 = U2048::from(2);
+        let (state, product) = commit(accumulator, &[0, 1], &[4u8, 9]).unwrap();
+        let (pi_i_0, pi_e_0) = open_at_key(accumulator, product, 0, 4u8).unwrap();
+        let (pi_i_1, pi_e_1) = open_at_key(accumulator, product, 1, 9u8).unwrap();
+
+        let mut items: Vec<VerifyItem<u8>> = (0..50)
+            .map(|index| {
+                if index % 2 == 0 {
+                    VerifyItem { old_state: accumulator, accumulator: state, key: 0, value: 4, pi_i: pi_i_0, pi_e: pi_e_0 }
+                }
+                else {
+                    VerifyItem { old_state: accumulator, accumulator: state, key: 1, value: 9, pi_i: pi_i_1, pi_e: pi_e_1 }
+                }
+            })
+            .collect();
+        assert_eq!(verify_batch(&items), true);
+
+        items[36].value = 6;
+        assert_eq!(verify_batch(&items), false);
+        assert_eq!(verify_batch::<u8>(&[]), true);
+    }
+
+    #[test]
+    fn test_codec() {
+        let accumulator: U2048 = U2048::from(2);
         let keys = vec![0, 1];
-        let values = vec![4, 7];
-        let (binary_vec, indices) = convert_key_value(&keys, &values);
-        assert_eq!(binary_vec, vec![false, false, false, false, false, true, false, false, false, false, false, false,
-            false, true, true, true]);
-        assert_eq!(indices, vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        let values: Vec<u8> = vec![4, 7];
+        let commitment = Commitment::from(commit(accumulator, &keys, &values).unwrap());
+
+        let encoded = commitment.encode();
+        assert_eq!(encoded.len(), 512);
+        let decoded = Commitment::decode(&mut &encoded[..]).unwrap();
+        assert_eq!(decoded, commitment);
+        assert_eq!(Commitment::decode(&mut &encoded[..511]).is_err(), true);
+
+        // Each value is a 256 byte big-endian integer, state first
+        let mut expected = vec![0u8; 512];
+        expected[254] = 0x01;
+        expected[255] = 0x02;
+        expected[511] = 0x03;
+        let known = Commitment { state: U2048::from(0x0102), product: U2048::from(3) };
+        assert_eq!(known.encode(), expected);
+        assert_eq!(Commitment::decode(&mut &expected[..]).unwrap(), known);
+
+        let opening = open_at_key(accumulator, decoded.product, 1, 7u8).unwrap();
+        let (pi_i, pi_e) = <(Witness, Witness)>::decode(&mut &opening.encode()[..]).unwrap();
+        assert_eq!((pi_i, pi_e), opening);
+        assert_eq!(verify_at_key(accumulator, decoded.state, 1, 7u8, pi_i, pi_e).is_ok(), true);
     }
 
+    #[cfg(feature = "std")]
     #[test]
-    fn test_vc_open_and_verify() {
+    fn test_serde() {
         let accumulator: U2048 = U2048::from(2);
         let keys = vec![0, 1];
-        let values = vec![4, 7];
-        let (new_accumulator, product) = commit(accumulator, &keys, &values);
+        let values: Vec<u8> = vec![4, 7];
+        let commitment = Commitment::from(commit(accumulator, &keys, &values).unwrap());
+        let opening = open_at_key(accumulator, commitment.product, 1, 7u8).unwrap();
+
+        let json = serde_json::to_string(&(commitment, opening)).unwrap();
+        let (commitment, (pi_i, pi_e)): (Commitment, (Witness, Witness)) = serde_json::from_str(&json).unwrap();
+        assert_eq!((pi_i, pi_e), opening);
+        assert_eq!(verify_at_key(accumulator, commitment.state, 1, 7u8, pi_i, pi_e).is_ok(), true);
+    }
+
+    #[test]
+    fn test_vc_batch_open_and_verify() {
+        let accumulator: U2048 = U2048::from(2);
+        let keys = vec![0, 1, 2];
+        let values: Vec<u8> = vec![4, 7, 9];
+        let (new_accumulator, product) = commit(accumulator, &keys, &values).unwrap();
+
+        let (pi_i, pi_e) = open_at_keys(accumulator, product, &keys, &values).unwrap();
+        assert_eq!(verify_at_keys(accumulator, new_accumulator, &keys, &values, pi_i, pi_e).is_ok(), true);
+        assert_eq!(verify_at_keys(accumulator, new_accumulator, &keys, &[4u8, 7, 8], pi_i, pi_e).is_ok(), false);
+
+        // Values that are not committed cannot be opened
+        assert_eq!(open_at_keys(accumulator, product, &keys, &[4u8, 7, 8]), Err(VcError::NotCommitted));
+        assert_eq!(open_at_keys(accumulator, product, &keys, &[4u8, 7, 10]), Err(VcError::NotCommitted));
+        assert_eq!(open_at_keys(accumulator, product, &[0, 1, 3], &values), Err(VcError::NotCommitted));
+    }
+
+    #[test]
+    fn test_vc_batch_open_and_verify_sparse_keys() {
+        let accumulator: U2048 = U2048::from(2);
+        let keys = vec![0, 5, 9];
+        let values: Vec<u8> = vec![3, 200, 17];
+        let (new_accumulator, product) = commit(accumulator, &keys, &values).unwrap();
+
+        let (pi_i, pi_e) = open_at_keys(accumulator, product, &keys, &values).unwrap();
+        assert_eq!(verify_at_keys(accumulator, new_accumulator, &keys, &values, pi_i, pi_e).is_ok(), true);
+
+        // Tampering with a single value invalidates the whole batch
+        assert_eq!(verify_at_keys(accumulator, new_accumulator, &keys, &[3u8, 201, 17], pi_i, pi_e).is_ok(), false);
+        assert_eq!(verify_at_keys(accumulator, new_accumulator, &[0, 5, 8], &values, pi_i, pi_e).is_ok(), false);
+    }
+
+    #[test]
+    fn test_to_binary_with() {
+        let elem: u16 = 0x1234;
+        let mixed = vec![false, false, true, true, false, true, false, false,
+                         false, false, false, true, false, false, true, false];
+        let little = vec![false, false, true, false, true, true, false, false,
+                          false, true, false, false, true, false, false, false];
+        let big = vec![false, false, false, true, false, false, true, false,
+                       false, false, true, true, false, true, false, false];
+
+        assert_eq!(to_binary_with(elem, Endianness::Mixed), mixed);
+        assert_eq!(to_binary_with(elem, Endianness::Mixed), to_binary(elem));
+        assert_eq!(to_binary_with(elem, Endianness::Little), little);
+        assert_eq!(to_binary_with(elem, Endianness::Big), big);
+
+        assert_eq!(from_binary_with::<u16>(&mixed, Endianness::Mixed), elem);
+        assert_eq!(from_binary_with::<u16>(&little, Endianness::Little), elem);
+        assert_eq!(from_binary_with::<u16>(&big, Endianness::Big), elem);
+    }
+
+    #[test]
+    fn test_to_binary_u32() {
+        let bv = to_binary(1u32);
+        assert_eq!(bv.len(), 32);
+        assert_eq!(bv[7], true);
+        assert_eq!(bv.iter().filter(|bit| **bit).count(), 1);
+    }
+
+    #[test]
+    fn test_convert_u32() {
+        let keys = vec![1, 3];
+        let values: Vec<u32> = vec![70_000, 65_535];
+        let (binary_vec, indices) = convert_key_value(&keys, &values).unwrap();
+        assert_eq!(binary_vec.len(), 64);
+        assert_eq!(indices[..32].to_vec(), (32..64).collect::<Vec<usize>>());
+        assert_eq!(indices[32..].to_vec(), (96..128).collect::<Vec<usize>>());
+    }
 
-        let (pi_i, pi_e) = open_at_key(accumulator, product, 1, 7);
+    #[test]
+    fn test_vc_open_and_verify_u32() {
+        let accumulator: U2048 = U2048::from(2);
+        let keys = vec![1, 2];
+        let values: Vec<u32> = vec![70_001, 65_535];
+        let (new_accumulator, product) = commit(accumulator, &keys, &values).unwrap();
+
+        let (pi_i, pi_e) = open_at_key(accumulator, product, 2, 65_535u32).unwrap();
+        assert_eq!(verify_at_key(accumulator, new_accumulator, 2, 65_535u32, pi_i, pi_e).is_ok(), true);
+        assert_eq!(verify_at_key(accumulator, new_accumulator, 1, 65_535u32, pi_i, pi_e).is_ok(), false);
+        assert_eq!(verify_at_key(accumulator, new_accumulator, 2, 70_001u32, pi_i, pi_e).is_ok(), false);
+    }
+
+    #[test]
+    fn test_vc_open_and_verify_large_value() {
+        let accumulator: U2048 = U2048::from(2);
+        let value: u32 = 1_000_000;
+        let (new_accumulator, product) = commit(accumulator, &[3], &[value]).unwrap();
+
+        let (pi_i, pi_e) = open_at_key(accumulator, product, 3, value).unwrap();
+        assert_eq!(verify_at_key(accumulator, new_accumulator, 3, value, pi_i, pi_e).is_ok(), true);
+        assert_eq!(verify_at_key(accumulator, new_accumulator, 3, 999_002u32, pi_i, pi_e).is_ok(), false);
+    }
+
+    #[test]
+    fn test_convert_key_bytes() {
+        let (binary_vec, indices) = convert_key_bytes(&[1, 2], &[vec![6], vec![]], 2).unwrap();
+        assert_eq!(binary_vec, vec![false, false, false, false, false, true, true, false, false, false, false, false,
+            false, false, false, false, false, false, false, false, false, false, false, false, false, false, false,
+            false, false, false, false, false]);
+        assert_eq!(indices, (16..48).collect::<Vec<usize>>());
+
+        // Longer values are truncated to the width
+        let (binary_vec, _) = convert_key_bytes(&[0], &[vec![1, 2, 3]], 1).unwrap();
+        assert_eq!(binary_vec, vec![false, false, false, false, false, false, false, true]);
+    }
+
+    #[test]
+    fn test_vc_bytes_open_and_verify() {
+        let accumulator: U2048 = U2048::from(2);
+        let keys = vec![0, 1, 2];
+        let values = vec![vec![0xAB], vec![], vec![0x12, 0x34]];
+        let (new_accumulator, product) = commit_bytes(accumulator, &keys, &values, 2).unwrap();
+
+        let (pi_i, pi_e) = open_at_key_bytes(accumulator, product, 2, &[0x12, 0x34], 2).unwrap();
+        assert_eq!(verify_at_key_bytes(accumulator, new_accumulator, 2, &[0x12, 0x34], 2, pi_i, pi_e), true);
+        assert_eq!(verify_at_key_bytes(accumulator, new_accumulator, 2, &[0x34, 0x12], 2, pi_i, pi_e), false);
+
+        // Short values verify against their zero-padded form
+        let (pi_i, pi_e) = open_at_key_bytes(accumulator, product, 0, &[0xAB], 2).unwrap();
+        assert_eq!(verify_at_key_bytes(accumulator, new_accumulator, 0, &[0xAB, 0x00], 2, pi_i, pi_e), true);
+
+        // An empty value is committed as all zero bits
+        let (pi_i, pi_e) = open_at_key_bytes(accumulator, product, 1, &[], 2).unwrap();
+        assert_eq!(verify_at_key_bytes(accumulator, new_accumulator, 1, &[], 2, pi_i, pi_e), true);
+        assert_eq!(verify_at_key_bytes(accumulator, new_accumulator, 1, &[0x00, 0x00], 2, pi_i, pi_e), true);
+        assert_eq!(verify_at_key_bytes(accumulator, new_accumulator, 1, &[0x01], 2, pi_i, pi_e), false);
+
+        // Values that differ from the committed ones cannot be opened
+        assert_eq!(open_at_key_bytes(accumulator, product, 2, &[0x34, 0x12], 2), Err(VcError::NotCommitted));
+        assert_eq!(open_at_key_bytes(accumulator, product, 0, &[0xAA], 2), Err(VcError::NotCommitted));
+        assert_eq!(open_at_key_bytes(accumulator, product, 1, &[0x01], 2), Err(VcError::NotCommitted));
+    }
+
+    #[test]
+    fn test_length_prefixed() {
+        let accumulator: U2048 = U2048::from(2);
+        let (state, product) = commit_length_prefixed(accumulator, 3, b"hi").unwrap();
+        let (binary_vec, indices) = convert_length_prefixed(3, b"hi").unwrap();
+        assert_eq!(indices, (3..51).collect::<Vec<usize>>());
+        assert_eq!(from_binary::<u32>(&binary_vec[..32]), 2);
+
+        let (pi_i, pi_e) = open_length_prefixed(accumulator, product, 3, b"hi").unwrap();
+        assert_eq!(verify_length_prefixed(accumulator, state, 3, b"hi", pi_i, pi_e), Ok(()));
+        assert_eq!(verify_length_prefixed(accumulator, state, 3, b"h", pi_i, pi_e).is_ok(), false);
+        assert_eq!(verify_length_prefixed(accumulator, state, 3, b"hi\0", pi_i, pi_e).is_ok(), false);
+        assert_eq!(verify_length_prefixed(accumulator, state, 3, b"ho", pi_i, pi_e).is_ok(), false);
+        assert_eq!(convert_length_prefixed(usize::max_value(), b"hi"), Err(VcError::KeyOutOfRange));
+
+        // Only the committed value can be opened
+        assert_eq!(open_length_prefixed(accumulator, product, 3, b"h"), Err(VcError::NotCommitted));
+        assert_eq!(open_length_prefixed(accumulator, product, 3, b"hi\0"), Err(VcError::NotCommitted));
+        assert_eq!(open_length_prefixed(accumulator, product, 3, b"ho"), Err(VcError::NotCommitted));
+    }
+
+    #[test]
+    fn test_merge() {
+        let accumulator: U2048 = U2048::from(2);
+        let (state_a, product_a) = commit(accumulator, &[0, 1], &[5u8, 7]).unwrap();
+        let (state_b, product_b) = commit(accumulator, &[2, 3], &[9u8, 4]).unwrap();
+        let shard_a = (state_a, product_a, 0..2);
+        let shard_b = (state_b, product_b, 2..4);
+        let (state, product) = merge(accumulator, &[shard_a.clone(), shard_b.clone()]).unwrap();
+        assert_eq!((state, product), commit(accumulator, &[0, 1, 2, 3], &[5u8, 7, 9, 4]).unwrap());
+        assert_eq!(merge(accumulator, &[]), Ok((accumulator, U2048::from(1))));
+
+        // Keys from both shards verify against the merged state
+        for &(key, value) in [(0, 5u8), (3, 4)].iter() {
+            let (pi_i, pi_e) = open_at_key(accumulator, product, key, value).unwrap();
+            assert_eq!(verify_at_key(accumulator, state, key, value, pi_i, pi_e), Ok(()));
+        }
+
+        let (state_c, product_c) = commit(accumulator, &[1], &[7u8]).unwrap();
+        assert_eq!(merge(accumulator, &[shard_a.clone(), shard_b.clone(), (state_c, product_c, 1..2)]), Err(VcError::OverlappingIndices));
+        assert_eq!(merge(accumulator, &[shard_a.clone(), (state_a, product_b, 2..4)]), Err(VcError::StateMismatch));
 
-        assert_eq!(verify_at_key(accumulator, new_accumulator, 1, 7, pi_i, pi_e), true);
-        assert_eq!(verify_at_key(accumulator, new_accumulator, 0, 7, pi_i, pi_e), false);
-        assert_eq!(verify_at_key(accumulator, new_accumulator, 1, 4, pi_i, pi_e), false);
+        // Key 1 committed as 7 and 8 by two shards shares no set bit but the key ranges overlap
+        let (state_7, product_7) = commit(accumulator, &[1], &[7u8]).unwrap();
+        let (state_8, product_8) = commit(accumulator, &[1], &[8u8]).unwrap();
+        assert_eq!(merge(accumulator, &[(state_7, product_7, 1..2), (state_8, product_8, 1..2)]), Err(VcError::OverlappingIndices));
+        assert_eq!(merge(accumulator, &[(state_7, product_7, 0..2), (state_8, product_8, 1..3)]), Err(VcError::OverlappingIndices));
+    }
+
+    #[test]
+    fn test_big_value() {
+        let accumulator: U2048 = U2048::from(2);
+        // 10^18 as a 32 byte little endian amount
+        let mut amount: Vec<u8> = vec![0x00, 0x00, 0x64, 0xa7, 0xb3, 0xb6, 0xe0, 0x0d];
+        amount.resize(32, 0);
+        let big = BigValue::new(5);
+        assert_eq!(BigValue::slots(amount.len()), 36);
+
+        let (state, product) = big.commit(accumulator, &amount).unwrap();
+        let (binary_vec, indices) = convert_length_prefixed(40, &amount).unwrap();
+        assert_eq!((state, product), binary::commit(accumulator, &binary_vec, &indices));
+
+        let openings = big.open_big(accumulator, product, &amount).unwrap();
+        assert_eq!(openings.len(), 36);
+        assert_eq!(big.verify_big(accumulator, state, &amount, &openings), Ok(()));
+        assert_eq!(big.verify_big(accumulator, state, &amount[..31], &openings[..35]).is_ok(), false);
+        assert_eq!(big.verify_big(accumulator, state, &amount, &openings[..35]), Err(VerifyError::LengthMismatch));
+
+        // A shorter value cannot be opened
+        assert_eq!(big.open_big(accumulator, product, &amount[..31]), Err(VcError::NotCommitted));
+        assert_eq!(big.open_big(accumulator, product, &[]), Err(VcError::NotCommitted));
+
+        // A zero-length value only reserves the keys of its length
+        let (state, product) = big.commit(accumulator, &[]).unwrap();
+        assert_eq!(product, U2048::from(1));
+        let openings = big.open_big(accumulator, product, &[]).unwrap();
+        assert_eq!(big.verify_big(accumulator, state, &[], &openings), Ok(()));
+        assert_eq!(big.verify_big(accumulator, state, &[0], &[openings[0], openings[1], openings[2], openings[3], openings[0]]).is_ok(), false);
+        assert_eq!(BigValue::new(usize::max_value()).commit(accumulator, &amount), Err(VcError::KeyOutOfRange));
+    }
+
+    #[test]
+    fn test_packed() {
+        let accumulator: U2048 = U2048::from(2);
+        let (state, product) = commit_packed(accumulator, 2, &[1u8, 2, 3]).unwrap();
+        assert_eq!((state, product), commit(accumulator, &[6, 7, 8], &[1u8, 2, 3]).unwrap());
+
+        let (pi_i, pi_e) = open_packed(accumulator, product, 2, 3, 1, 2u8).unwrap();
+        assert_eq!(verify_packed(accumulator, state, 2, 3, 1, 2u8, pi_i, pi_e), Ok(()));
+        assert_eq!(verify_packed(accumulator, state, 2, 3, 1, 1u8, pi_i, pi_e).is_ok(), false);
+        assert_eq!(verify_packed(accumulator, state, 2, 3, 2, 2u8, pi_i, pi_e).is_ok(), false);
+        assert_eq!(open_packed(accumulator, product, 2, 3, 3, 2u8), Err(VcError::KeyOutOfRange));
     }
 
     #[test]
     fn test_get_key_value_elem() {
         let (key, value): (usize, u8) = (0, 5);
-        let elem = get_key_value_elem(key, value);
+        let elem = get_key_value_elem(key, value).unwrap();
 
         let bv = to_binary(value);
         let indices: Vec<usize> = (0..8).collect();
@@ -127,4 +2698,77 @@ mod tests {
         assert_eq!(state, subroutines::mod_exp(U2048::from(2), elem, U2048::from_dec_str(MODULUS).unwrap()))
     }
 
+    #[test]
+    fn test_get_key_value_elem_consistency() {
+        let accumulator: U2048 = U2048::from(2);
+        for key in 0..3 {
+            for &value in [0u8, 5, 128, 255].iter() {
+                let elem = get_key_value_elem(key, value).unwrap();
+                let (state, product) = commit(accumulator, &[key], &[value]).unwrap();
+                assert_eq!(elem, product);
+
+                let (binary_vec, indices) = convert_key_value(&[key], &[value]).unwrap();
+                assert_eq!(binary::get_bit_elems(&binary_vec, &indices).0, elem);
+                let primes: Vec<U2048> = element_primes(&[key], &[value]).unwrap().iter().map(|(_, prime)| *prime).collect();
+                assert_eq!(subroutines::prime_product(&primes), elem);
+
+                let (pi_i, _) = open_at_key(accumulator, product, key, value).unwrap();
+                match pi_i {
+                    Witness::MemWit(mem_wit) => assert_eq!(witnesses::verify_mem_wit(state, mem_wit, elem), true),
+                    _ => panic!(),
+                }
+            }
+        }
+        assert_eq!(get_key_value_elem(0, 0u8), Ok(U2048::from(1)));
+        assert_eq!(get_key_value_elem(0, 128u8), Ok(binary::hash_index(0)));
+        assert_eq!(get_key_value_elem(1, 1u8), Ok(binary::hash_index(15)));
+    }
+
+    #[test]
+    fn test_element_primes() {
+        let accumulator: U2048 = U2048::from(2);
+        let (keys, values) = ([0, 1], [4u8, 7]);
+        let primes = element_primes(&keys, &values).unwrap();
+        let indices: Vec<usize> = primes.iter().map(|(index, _)| *index).collect();
+        assert_eq!(indices, vec![5, 13, 14, 15]);
+        for (index, prime) in primes.iter() {
+            assert_eq!(*prime, binary::hash_index(*index));
+        }
+
+        let mut product = U2048::from(1);
+        for (_, prime) in primes.iter() {
+            product *= *prime;
+        }
+        assert_eq!(product, commit(accumulator, &keys, &values).unwrap().1);
+        assert_eq!(element_primes(&[0, 0], &values), Err(VcError::OverlappingIndices));
+    }
+
+    #[test]
+    fn test_product_of() {
+        let accumulator: U2048 = U2048::from(2);
+        let keys = vec![0, 1, 2];
+        let values: Vec<u8> = vec![4, 7, 9];
+        let (_, product) = commit(accumulator, &keys, &values).unwrap();
+        assert_eq!(product_of(&keys, &values), Ok(product));
+        assert_eq!(product_of::<u8>(&[], &[]), Ok(U2048::from(1)));
+        assert_eq!(matches_product(product, &keys, &values), true);
+
+        // A forged value
+        assert_eq!(matches_product(product, &keys, &[4u8, 7, 8]), false);
+        assert_eq!(matches_product(product, &keys, &[4u16, 7, 9]), false);
+        assert_eq!(matches_product(product, &[0, 1, 1], &values), false);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_get_key_value_elem_cached() {
+        let mut cache = binary::PrimeCache::new();
+        for key in 0..4 {
+            for value in 0..16u8 {
+                assert_eq!(get_key_value_elem_cached(key, value, &mut cache), get_key_value_elem(key, value));
+            }
+        }
+        assert_eq!(cache.len(), 32);
+    }
+
 }
\ No newline at end of file